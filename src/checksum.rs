@@ -0,0 +1,179 @@
+//! The checksum trailer (see `Config::checksum`)
+//!
+//! The trailer is the CRC32C (Castagnoli) of all preceding bytes, including
+//! any envelope, as a little-endian `u32`. Its byte order is fixed, as it is
+//! verified before an envelope could change the configured one.
+
+// stdlib imports
+#[cfg(feature = "std")]
+use std::io;
+// local imports
+use crate::config::Config;
+use crate::deserialize::Error;
+use crate::envelope;
+#[cfg(feature = "std")]
+use crate::input::{Input, IoReader};
+
+
+pub(crate) const LEN: usize = 4;
+
+/// Reversed CRC32C polynomial
+const POLY: u32 = 0x82F6_3B78;
+
+/// Tables for slice-by-8. `TABLE[ k ][ x ]` is the CRC of byte `x` followed by
+/// `k` zero bytes.
+static TABLE: [[u32; 256]; 8] = table( );
+
+const fn table( ) -> [[u32; 256]; 8] {
+  let mut res = [[0u32; 256]; 8];
+  let mut i = 0;
+  while i < 256 {
+    let mut crc = i as u32;
+    let mut j = 0;
+    while j < 8 {
+      crc = if crc & 1 != 0 { ( crc >> 1 ) ^ POLY } else { crc >> 1 };
+      j += 1;
+    }
+    res[ 0 ][ i ] = crc;
+    i += 1;
+  }
+  let mut i = 0;
+  while i < 256 {
+    let mut k = 1;
+    while k < 8 {
+      let prev = res[ k - 1 ][ i ];
+      res[ k ][ i ] = ( prev >> 8 ) ^ res[ 0 ][ ( prev & 0xFF ) as usize ];
+      k += 1;
+    }
+    i += 1;
+  }
+  res
+}
+
+/// Extends the CRC32C `crc` of some bytes by `xs`. The CRC of no bytes is 0.
+pub(crate) fn crc32c( crc: u32, xs: &[u8] ) -> u32 {
+  let mut crc = !crc;
+  let mut chunks = xs.chunks_exact( 8 );
+  for x in &mut chunks {
+    let lo = u32::from_le_bytes( [ x[ 0 ], x[ 1 ], x[ 2 ], x[ 3 ] ] ) ^ crc;
+    let hi = u32::from_le_bytes( [ x[ 4 ], x[ 5 ], x[ 6 ], x[ 7 ] ] );
+    crc = TABLE[ 7 ][ ( lo & 0xFF ) as usize ]
+      ^ TABLE[ 6 ][ ( ( lo >> 8 ) & 0xFF ) as usize ]
+      ^ TABLE[ 5 ][ ( ( lo >> 16 ) & 0xFF ) as usize ]
+      ^ TABLE[ 4 ][ ( lo >> 24 ) as usize ]
+      ^ TABLE[ 3 ][ ( hi & 0xFF ) as usize ]
+      ^ TABLE[ 2 ][ ( ( hi >> 8 ) & 0xFF ) as usize ]
+      ^ TABLE[ 1 ][ ( ( hi >> 16 ) & 0xFF ) as usize ]
+      ^ TABLE[ 0 ][ ( hi >> 24 ) as usize ];
+  }
+  for &x in chunks.remainder( ) {
+    crc = ( crc >> 8 ) ^ TABLE[ 0 ][ ( ( crc ^ x as u32 ) & 0xFF ) as usize ];
+  }
+  !crc
+}
+
+/// The trailer of `xs`
+#[inline]
+pub(crate) fn trailer( xs: &[u8] ) -> [u8; LEN] {
+  crc32c( 0, xs ).to_le_bytes( )
+}
+
+/// Verifies the trailer at the end of `xs`. Returns the bytes preceding it.
+pub(crate) fn verify( xs: &[u8] ) -> Result< &[u8], Error > {
+  let Some( n ) = xs.len( ).checked_sub( LEN ) else {
+    return Err( Error::InsufficientData );
+  };
+  let ( data, found ) = xs.split_at( n );
+  if found != trailer( data ) {
+    return Err( Error::ChecksumMismatch );
+  }
+  Ok( data )
+}
+
+/// Verifies and strips the trailer of `xs`, if it has one: by the flag of its
+/// envelope, if configured, or else by `config.checksum`. A malformed envelope
+/// is left to be reported when it is read.
+pub(crate) fn strip( xs: &[u8], config: Config ) -> Result< &[u8], Error > {
+  let checksum = if config.envelope {
+    let header = xs.get( ..envelope::LEN ).and_then( |h| h.try_into( ).ok( ) );
+    header.and_then( |h| envelope::decode( h, config ).ok( ) ).is_some_and( |c| c.checksum )
+  } else {
+    config.checksum
+  };
+  if checksum { verify( xs ) } else { Ok( xs ) }
+}
+
+/// Writer that computes the checksum of the bytes passing through it
+#[cfg(feature = "std")]
+pub(crate) struct CrcWriter< W: io::Write > {
+  inner: W,
+  crc: u32
+}
+
+#[cfg(feature = "std")]
+impl< W: io::Write > CrcWriter< W > {
+  #[inline]
+  pub(crate) fn new( inner: W ) -> Self {
+    CrcWriter { inner, crc: 0 }
+  }
+
+  /// Writes the trailer
+  pub(crate) fn finish( mut self ) -> io::Result< () > {
+    self.inner.write_all( &self.crc.to_le_bytes( ) )
+  }
+}
+
+#[cfg(feature = "std")]
+impl< W: io::Write > io::Write for CrcWriter< W > {
+  #[inline]
+  fn write( &mut self, xs: &[u8] ) -> io::Result< usize > {
+    let n = self.inner.write( xs )?;
+    self.crc = crc32c( self.crc, &xs[ ..n ] );
+    Ok( n )
+  }
+
+  #[inline]
+  fn flush( &mut self ) -> io::Result< () > {
+    self.inner.flush( )
+  }
+}
+
+/// Reader that computes the checksum of the bytes passing through it
+#[cfg(feature = "std")]
+pub(crate) struct CrcReader< R: io::Read > {
+  inner: R,
+  crc: u32
+}
+
+#[cfg(feature = "std")]
+impl< R: io::Read > CrcReader< R > {
+  /// Continues the checksum `crc` of bytes read before, which is 0 if none
+  #[inline]
+  pub(crate) fn new( inner: R, crc: u32 ) -> Self {
+    CrcReader { inner, crc }
+  }
+
+  /// Reads and verifies the trailer. With `strict`, the reader must end after
+  /// it.
+  pub(crate) fn finish( self, strict: bool ) -> Result< (), Error > {
+    let mut input = IoReader::new( self.inner );
+    let found: [u8; LEN] = Input::read_array( &mut input )?;
+    if found != self.crc.to_le_bytes( ) {
+      return Err( Error::ChecksumMismatch );
+    }
+    if strict {
+      Input::end( &mut input )?;
+    }
+    Ok( () )
+  }
+}
+
+#[cfg(feature = "std")]
+impl< R: io::Read > io::Read for CrcReader< R > {
+  #[inline]
+  fn read( &mut self, xs: &mut [u8] ) -> io::Result< usize > {
+    let n = self.inner.read( xs )?;
+    self.crc = crc32c( self.crc, &xs[ ..n ] );
+    Ok( n )
+  }
+}
@@ -0,0 +1,314 @@
+/// The byte order in which multi-byte primitives are encoded
+///
+/// This applies to every fixed-width integer and float, including length
+/// prefixes and enum discriminants.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ByteOrder {
+  /// Least significant byte first. Portable.
+  Little,
+  /// Most significant byte first. Portable.
+  Big,
+  /// The byte order of the machine. Fastest, but not portable between
+  /// machines of different endianness.
+  #[default]
+  Native
+}
+
+impl ByteOrder {
+  #[inline]
+  pub(crate) fn encode_u16( self, v: u16 ) -> [u8; 2] {
+    match self {
+      ByteOrder::Little => v.to_le_bytes( ),
+      ByteOrder::Big    => v.to_be_bytes( ),
+      ByteOrder::Native => v.to_ne_bytes( )
+    }
+  }
+
+  #[inline]
+  pub(crate) fn encode_u32( self, v: u32 ) -> [u8; 4] {
+    match self {
+      ByteOrder::Little => v.to_le_bytes( ),
+      ByteOrder::Big    => v.to_be_bytes( ),
+      ByteOrder::Native => v.to_ne_bytes( )
+    }
+  }
+
+  #[inline]
+  pub(crate) fn encode_u64( self, v: u64 ) -> [u8; 8] {
+    match self {
+      ByteOrder::Little => v.to_le_bytes( ),
+      ByteOrder::Big    => v.to_be_bytes( ),
+      ByteOrder::Native => v.to_ne_bytes( )
+    }
+  }
+
+  #[inline]
+  pub(crate) fn encode_u128( self, v: u128 ) -> [u8; 16] {
+    match self {
+      ByteOrder::Little => v.to_le_bytes( ),
+      ByteOrder::Big    => v.to_be_bytes( ),
+      ByteOrder::Native => v.to_ne_bytes( )
+    }
+  }
+
+  #[inline]
+  pub(crate) fn decode_u16( self, xs: [u8; 2] ) -> u16 {
+    match self {
+      ByteOrder::Little => u16::from_le_bytes( xs ),
+      ByteOrder::Big    => u16::from_be_bytes( xs ),
+      ByteOrder::Native => u16::from_ne_bytes( xs )
+    }
+  }
+
+  #[inline]
+  pub(crate) fn decode_u32( self, xs: [u8; 4] ) -> u32 {
+    match self {
+      ByteOrder::Little => u32::from_le_bytes( xs ),
+      ByteOrder::Big    => u32::from_be_bytes( xs ),
+      ByteOrder::Native => u32::from_ne_bytes( xs )
+    }
+  }
+
+  #[inline]
+  pub(crate) fn decode_u64( self, xs: [u8; 8] ) -> u64 {
+    match self {
+      ByteOrder::Little => u64::from_le_bytes( xs ),
+      ByteOrder::Big    => u64::from_be_bytes( xs ),
+      ByteOrder::Native => u64::from_ne_bytes( xs )
+    }
+  }
+
+  #[inline]
+  pub(crate) fn decode_u128( self, xs: [u8; 16] ) -> u128 {
+    match self {
+      ByteOrder::Little => u128::from_le_bytes( xs ),
+      ByteOrder::Big    => u128::from_be_bytes( xs ),
+      ByteOrder::Native => u128::from_ne_bytes( xs )
+    }
+  }
+}
+
+/// The encoding of integers wider than a byte
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntEncoding {
+  /// Every integer occupies its full width (e.g., 4 bytes for a `u32`)
+  #[default]
+  Fixed,
+  /// Unsigned LEB128. Small values occupy fewer bytes, while large values may
+  /// occupy one byte more than their full width. Signed integers are first
+  /// ZigZag-mapped (0, -1, 1, -2, ... to 0, 1, 2, 3, ...), such that values
+  /// of small magnitude remain small.
+  Varint
+}
+
+/// The encoding of `char`s
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CharEncoding {
+  /// UTF-8, which occupies 1 to 4 bytes
+  #[default]
+  Utf8,
+  /// The Unicode scalar value, encoded as a `u32` (i.e., by `int_encoding`)
+  CodePoint
+}
+
+/// The width of an integer prefix, such as the length of a string
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntWidth {
+  U8,
+  U16,
+  U32,
+  U64,
+  /// Unsigned LEB128, with at most 64 bits
+  Varint
+}
+
+impl IntWidth {
+  /// The largest value representable in this width
+  #[inline]
+  pub(crate) fn max( self ) -> u64 {
+    match self {
+      IntWidth::U8     => u8::MAX as u64,
+      IntWidth::U16    => u16::MAX as u64,
+      IntWidth::U32    => u32::MAX as u64,
+      IntWidth::U64    => u64::MAX,
+      IntWidth::Varint => u64::MAX
+    }
+  }
+}
+
+/// ZigZag-maps a signed integer onto an unsigned integer. Sign-extending a
+/// narrower integer before mapping it does not change its result.
+#[inline]
+pub(crate) fn zigzag_encode( v: i64 ) -> u64 {
+  ( ( v << 1 ) ^ ( v >> 63 ) ) as u64
+}
+
+/// Inverse of `zigzag_encode`
+#[inline]
+pub(crate) fn zigzag_decode( v: u64 ) -> i64 {
+  ( ( v >> 1 ) as i64 ) ^ -( ( v & 1 ) as i64 )
+}
+
+/// 128-bit variant of `zigzag_encode`
+#[inline]
+pub(crate) fn zigzag_encode_128( v: i128 ) -> u128 {
+  ( ( v << 1 ) ^ ( v >> 127 ) ) as u128
+}
+
+/// Inverse of `zigzag_encode_128`
+#[inline]
+pub(crate) fn zigzag_decode_128( v: u128 ) -> i128 {
+  ( ( v >> 1 ) as i128 ) ^ -( ( v & 1 ) as i128 )
+}
+
+/// Bounds on untrusted input, which are checked during deserialization
+///
+/// Length prefixes are read from the input, so without limits a few bytes of
+/// input may ask for arbitrarily large allocations. By default, only the
+/// `size_hint` is bounded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+  /// Total number of bytes a single deserializer may consume
+  pub max_bytes: Option< u64 >,
+  /// Length of a single string or byte array, in bytes
+  pub max_len: Option< usize >,
+  /// Number of elements of a single sequence, or entries of a single map
+  pub max_elements: Option< usize >,
+  /// Upper bound on the `size_hint` of sequences and maps, which visitors
+  /// commonly use to preallocate
+  pub max_size_hint: usize
+}
+
+impl Default for Limits {
+  #[inline]
+  fn default( ) -> Self {
+    Limits {
+      max_bytes:     None,
+      max_len:       None,
+      max_elements:  None,
+      max_size_hint: 4096
+    }
+  }
+}
+
+/// The limit that was exceeded, as reported by `de::Error::LimitExceeded`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+  /// `Limits::max_bytes`
+  MaxBytes,
+  /// `Limits::max_len`
+  MaxLen,
+  /// `Limits::max_elements`
+  MaxElements
+}
+
+/// Encoding settings, shared by `BinSerializer` and `BinDeserializer`
+///
+/// Data can only be deserialized with the same configuration it was
+/// serialized with. The default configuration is the original encoding of
+/// this crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+  pub byte_order: ByteOrder,
+  /// Single bytes (`u8`, `i8`, `bool`) are never varint-encoded. Length
+  /// prefixes are configured separately.
+  pub int_encoding: IntEncoding,
+  /// Length prefix of strings (in bytes)
+  pub str_len: IntWidth,
+  /// Length prefix of byte arrays
+  pub bytes_len: IntWidth,
+  /// Length prefix of sequences
+  pub seq_len: IntWidth,
+  /// Length prefix of maps
+  pub map_len: IntWidth,
+  /// Enum discriminants. `U8` supports up to 256 variants.
+  pub variant_width: IntWidth,
+  pub char_encoding: CharEncoding,
+  /// Any nonzero byte deserializes as `true`, rather than failing with
+  /// `MalformedBool`. Serialization is unaffected.
+  pub lenient_bool: bool,
+  /// Deserialization fails on input that is valid, but indicates a mismatch
+  /// with the target type: trailing bytes, sequence and map elements that the
+  /// visitor does not consume, and duplicate map keys. Sets are encoded as
+  /// plain sequences, so duplicates in sets cannot be recognized. This does
+  /// not affect serialization.
+  pub strict: bool,
+  /// Bounds on the input. These do not affect serialization.
+  pub limits: Limits,
+  /// Maximum nesting depth of sequences, maps, structs, tuples, enums and
+  /// options. Both serialization and deserialization fail beyond it, rather
+  /// than overflow the stack on deeply recursive values. The default of 128
+  /// also rejects values that were accepted before the bound existed; raise
+  /// it for data nested deeper than that.
+  pub max_depth: usize,
+  /// Errors are wrapped in a `Context`, which records the path to the value
+  /// that failed (e.g., `config.servers[3].name`) and, for deserialization,
+  /// the byte offset. Paths are only assembled once an error occurs, but
+  /// wrapped errors are larger and must be unwrapped before matching. Requires
+  /// `alloc`.
+  pub error_context: bool,
+  /// Every value is prefixed with a type tag, such that it can be
+  /// deserialized without knowing its type, as with `deserialize_any`. This
+  /// is required by `#[serde(flatten)]`, untagged and internally tagged enums.
+  /// Structs are encoded as maps keyed by field name, and enums by variant
+  /// name. The encoding is larger, and incompatible with the default one.
+  pub self_describing: bool,
+  /// Reported by `is_human_readable`, which types like `IpAddr` use to choose
+  /// between a text and a compact encoding. Set it for compatibility with
+  /// data encoded before this option existed, which used text encodings.
+  pub human_readable: bool,
+  /// Serialized data starts with a header of magic bytes, the format version
+  /// and the encoding settings. When deserializing, the header is required,
+  /// and its settings replace those of this `Config`. Applies to the free
+  /// functions and `Options`. Otherwise, see `BinSerializer::write_envelope`
+  /// and `BinDeserializer::read_envelope`.
+  pub envelope: bool,
+  /// Serialized data ends with a CRC32C of all preceding bytes, which is
+  /// verified before the value is returned. Byte slices are verified before
+  /// decoding, while readers and writers compute it as the data passes.
+  /// With `envelope`, the header records it, and readers follow the header.
+  /// Applies to the free functions and `Options`.
+  pub checksum: bool
+}
+
+impl Default for Config {
+  #[inline]
+  fn default( ) -> Self {
+    Config {
+      byte_order:      ByteOrder::default( ),
+      int_encoding:    IntEncoding::default( ),
+      str_len:         IntWidth::U32,
+      bytes_len:       IntWidth::U64,
+      seq_len:         IntWidth::U32,
+      map_len:         IntWidth::U32,
+      variant_width:   IntWidth::U8,
+      char_encoding:   CharEncoding::Utf8,
+      lenient_bool:    false,
+      strict:          false,
+      limits:          Limits::default( ),
+      max_depth:       128,
+      error_context:   false,
+      self_describing: false,
+      human_readable:  false,
+      envelope:        false,
+      checksum:        false
+    }
+  }
+}
+
+impl Config {
+  /// The default configuration, but with varint-encoded integers, length
+  /// prefixes and enum discriminants
+  #[inline]
+  pub fn varint( ) -> Self {
+    Config {
+      int_encoding:  IntEncoding::Varint,
+      str_len:       IntWidth::Varint,
+      bytes_len:     IntWidth::Varint,
+      seq_len:       IntWidth::Varint,
+      map_len:       IntWidth::Varint,
+      variant_width: IntWidth::Varint,
+      ..Config::default( )
+    }
+  }
+}
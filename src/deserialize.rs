@@ -1,66 +1,547 @@
 // stdlib imports
-use std::str;
-use std::fmt;
-use std::mem::size_of;
+use core::str;
+use core::fmt;
+use core::marker::PhantomData;
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeSet;
+#[cfg(feature = "alloc")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
 // external library imports
 use serde::Deserializer;
-use serde::de::{self, EnumAccess, MapAccess, SeqAccess, VariantAccess};
-
+use serde::de::{self, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess};
+// local imports
+use crate::config::{CharEncoding, Config, IntEncoding, IntWidth, Limit, zigzag_decode, zigzag_decode_128};
+use crate::envelope;
+use crate::input::{Bytes, Input, SliceInput};
+#[cfg(feature = "alloc")]
+use crate::path::Path;
+use crate::path::Segment;
+use crate::tag;
+
+
+/// Deserializer from an `Input`, which is a byte slice by default
+pub struct BinDeserializer< 'de, R: Input< 'de > = SliceInput< 'de > > {
+  input: R,
+  config: Config,
+  /// Number of bytes that may still be consumed, by `Limits::max_bytes`
+  budget: u64,
+  /// Current nesting depth, bounded by `Config::max_depth`
+  depth: usize,
+  _marker: PhantomData< &'de () >
+}
 
-#[derive(Default)]
-pub struct BinDeserializer< 'de > {
-  input: &'de [u8]
+impl Default for BinDeserializer< '_ > {
+  #[inline]
+  fn default( ) -> Self {
+    BinDeserializer::new( &[ ], Config::default( ) )
+  }
 }
 
 impl< 'de > From< &'de [u8] > for BinDeserializer< 'de > {
   #[inline]
   fn from( input: &'de [u8] ) -> Self {
-    BinDeserializer { input }
+    BinDeserializer::new( input, Config::default( ) )
   }
 }
 
 impl< 'de > BinDeserializer< 'de > {
   #[inline]
-  fn peek_u8( &self ) -> Result< u8, Error > {
-    if self.input.len( ) > 0 {
-      Ok( self.input[ 0 ] )
-    } else {
-      Err( Error::InsufficientData )
+  pub fn new( input: &'de [u8], config: Config ) -> Self {
+    BinDeserializer::with_input( SliceInput::new( input ), config )
+  }
+}
+
+impl< 'de, R: Input< 'de > > BinDeserializer< 'de, R > {
+  #[inline]
+  pub fn with_input( input: R, config: Config ) -> Self {
+    let budget = config.limits.max_bytes.unwrap_or( u64::MAX );
+    BinDeserializer { input, config, budget, depth: 0, _marker: PhantomData }
+  }
+
+  #[inline]
+  pub fn into_input( self ) -> R {
+    self.input
+  }
+
+  /// Checks that all input was consumed
+  #[inline]
+  pub fn end( &mut self ) -> Result< (), Error > {
+    self.input.end( )
+  }
+
+  /// The configuration, including the settings adopted from an envelope
+  #[inline]
+  pub fn config( &self ) -> Config {
+    self.config
+  }
+
+  /// Reads the header of `Config::envelope`, and adopts its encoding
+  /// settings
+  pub fn read_envelope( &mut self ) -> Result< (), Error > {
+    let header = self.read_array::< { envelope::LEN } >( )?;
+    self.config = envelope::decode( header, self.config )?;
+    Ok( () )
+  }
+
+  /// Reads the fingerprint written by `BinSerializer::write_fingerprint`, and
+  /// checks that it is `expected` (see `crate::fingerprint`)
+  pub fn check_fingerprint( &mut self, expected: u64 ) -> Result< (), Error > {
+    if self.read_fixed_u64( )? != expected {
+      return Err( Error::TypeMismatch );
     }
+    Ok( () )
   }
 
+  /// Enters a nested value
   #[inline]
-  fn peek_u8_n< const N: usize >( &self ) -> Result< &[u8], Error > {
-    if self.input.len( ) >= N {
-      Ok( &self.input[ 0..N ] )
-    } else {
-      Err( Error::InsufficientData )
+  fn enter( &mut self ) -> Result< (), Error > {
+    if self.depth >= self.config.max_depth {
+      return Err( Error::DepthLimitExceeded );
+    }
+    self.depth += 1;
+    Ok( () )
+  }
+
+  /// Leaves a nested value, entered with `enter`
+  #[inline]
+  fn leave( &mut self ) {
+    self.depth -= 1;
+  }
+
+  /// Number of bytes read so far
+  #[inline]
+  pub fn offset( &self ) -> u64 {
+    self.config.limits.max_bytes.unwrap_or( u64::MAX ) - self.budget
+  }
+
+  /// With `Config::error_context`, records that `err` occurred within
+  /// `segment`, or at the top level. The offset is recorded where the error
+  /// is first located.
+  #[cold]
+  pub(crate) fn locate( &self, err: Error, segment: Option< Segment > ) -> Error {
+    #[cfg(feature = "alloc")]
+    if self.config.error_context {
+      let mut context = match err {
+        Error::Context( context ) => context,
+        error => Box::new( Context { offset: self.offset( ), path: Path::default( ), error } )
+      };
+      if let Some( segment ) = segment {
+        context.path.push_front( segment );
+      }
+      return Error::Context( context );
     }
+    let _ = segment;
+    err
   }
 
+  /// Checks that `n` more bytes may be read. Bytes are only accounted for
+  /// once read, such that `offset` excludes a failed read.
   #[inline]
-  fn peek_u8_nd( &self, n: usize ) -> Result< &[u8], Error > {
-    if self.input.len( ) >= n {
-      Ok( &self.input[ 0..n ] )
+  fn check_budget( &self, n: usize ) -> Result< (), Error > {
+    if n as u64 > self.budget {
+      Err( Error::LimitExceeded( Limit::MaxBytes ) )
     } else {
-      Err( Error::InsufficientData )
+      Ok( () )
+    }
+  }
+
+  #[inline]
+  fn read_u8( &mut self ) -> Result< u8, Error > {
+    self.check_budget( 1 )?;
+    let res = self.input.read_u8( )?;
+    self.budget -= 1;
+    Ok( res )
+  }
+
+  #[inline]
+  fn read_array< const N: usize >( &mut self ) -> Result< [u8; N], Error > {
+    self.check_budget( N )?;
+    let res = self.input.read_array::< N >( )?;
+    self.budget -= N as u64;
+    Ok( res )
+  }
+
+  #[inline]
+  fn read_bytes( &mut self, n: usize ) -> Result< Bytes< 'de, '_ >, Error > {
+    self.check_budget( n )?;
+    let res = self.input.read_bytes( n )?;
+    self.budget -= n as u64;
+    Ok( res )
+  }
+
+  #[cfg(feature = "alloc")]
+  #[inline]
+  fn read_byte_buf( &mut self, n: usize ) -> Result< Vec< u8 >, Error > {
+    self.check_budget( n )?;
+    let res = self.input.read_byte_buf( n )?;
+    self.budget -= n as u64;
+    Ok( res )
+  }
+
+  #[inline]
+  fn read_fixed_u16( &mut self ) -> Result< u16, Error > {
+    let xs = self.read_array::< 2 >( )?;
+    Ok( self.config.byte_order.decode_u16( xs ) )
+  }
+
+  #[inline]
+  fn read_fixed_u32( &mut self ) -> Result< u32, Error > {
+    let xs = self.read_array::< 4 >( )?;
+    Ok( self.config.byte_order.decode_u32( xs ) )
+  }
+
+  #[inline]
+  fn read_fixed_u64( &mut self ) -> Result< u64, Error > {
+    let xs = self.read_array::< 8 >( )?;
+    Ok( self.config.byte_order.decode_u64( xs ) )
+  }
+
+  /// Reads an unsigned LEB128 varint, whose value may not exceed `max`.
+  /// Encodings with redundant trailing zero-groups are rejected, such that
+  /// every value has exactly one encoding.
+  #[inline]
+  fn read_varint( &mut self, max: u64 ) -> Result< u64, Error > {
+    let mut res: u64 = 0;
+    let mut shift: u32 = 0;
+    loop {
+      let b = self.read_u8( )?;
+      let group = ( b & 0x7F ) as u64;
+      if shift > 0 && b == 0 {
+        // overlong. e.g., [0x80, 0x00] for 0
+        return Err( Error::MalformedVarint );
+      }
+      if shift >= 64 || ( group << shift ) >> shift != group {
+        // overflow
+        return Err( Error::MalformedVarint );
+      }
+      res |= group << shift;
+      if b & 0x80 == 0 {
+        break;
+      }
+      shift += 7;
+    }
+    if res > max {
+      return Err( Error::MalformedVarint );
     }
+    Ok( res )
   }
 
+  /// Reads an integer of the given width
   #[inline]
-  fn skip_unchecked( &mut self, n: usize ) {
-    self.input = &self.input[ n.. ];
+  fn read_int( &mut self, width: IntWidth ) -> Result< u64, Error > {
+    match width {
+      IntWidth::U8     => Ok( self.read_u8( )? as u64 ),
+      IntWidth::U16    => Ok( self.read_fixed_u16( )? as u64 ),
+      IntWidth::U32    => Ok( self.read_fixed_u32( )? as u64 ),
+      IntWidth::U64    => self.read_fixed_u64( ),
+      IntWidth::Varint => self.read_varint( u64::MAX )
+    }
+  }
+
+  /// Reads a length prefix of the given width
+  #[inline]
+  fn read_len( &mut self, width: IntWidth ) -> Result< usize, Error > {
+    // A length beyond the address space can never be satisfied
+    usize::try_from( self.read_int( width )? ).map_err( |_| Error::InsufficientData )
+  }
+
+  /// Reads the length prefix of a string or byte array
+  #[inline]
+  fn read_data_len( &mut self, width: IntWidth ) -> Result< usize, Error > {
+    let len = self.read_len( width )?;
+    match self.config.limits.max_len {
+      Some( max ) if len > max => Err( Error::LimitExceeded( Limit::MaxLen ) ),
+      _ => Ok( len )
+    }
+  }
+
+  /// Reads the length prefix of a sequence or map
+  #[inline]
+  fn read_count( &mut self, width: IntWidth ) -> Result< usize, Error > {
+    let len = self.read_len( width )?;
+    match self.config.limits.max_elements {
+      Some( max ) if len > max => Err( Error::LimitExceeded( Limit::MaxElements ) ),
+      _ => Ok( len )
+    }
+  }
+
+  #[inline]
+  fn read_i16( &mut self ) -> Result< i16, Error > {
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => Ok( self.read_fixed_u16( )? as i16 ),
+      IntEncoding::Varint => Ok( zigzag_decode( self.read_varint( u16::MAX as u64 )? ) as i16 )
+    }
+  }
+
+  #[inline]
+  fn read_i32( &mut self ) -> Result< i32, Error > {
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => Ok( self.read_fixed_u32( )? as i32 ),
+      IntEncoding::Varint => Ok( zigzag_decode( self.read_varint( u32::MAX as u64 )? ) as i32 )
+    }
+  }
+
+  #[inline]
+  fn read_i64( &mut self ) -> Result< i64, Error > {
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => Ok( self.read_fixed_u64( )? as i64 ),
+      IntEncoding::Varint => Ok( zigzag_decode( self.read_varint( u64::MAX )? ) )
+    }
+  }
+
+  /// 128-bit variant of `read_varint`
+  #[inline]
+  fn read_varint_128( &mut self ) -> Result< u128, Error > {
+    let mut res: u128 = 0;
+    let mut shift: u32 = 0;
+    loop {
+      let b = self.read_u8( )?;
+      let group = ( b & 0x7F ) as u128;
+      if shift > 0 && b == 0 {
+        return Err( Error::MalformedVarint );
+      }
+      if shift >= 128 || ( group << shift ) >> shift != group {
+        return Err( Error::MalformedVarint );
+      }
+      res |= group << shift;
+      if b & 0x80 == 0 {
+        return Ok( res );
+      }
+      shift += 7;
+    }
+  }
+
+  #[inline]
+  fn read_i128( &mut self ) -> Result< i128, Error > {
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => Ok( self.read_u128( )? as i128 ),
+      IntEncoding::Varint => Ok( zigzag_decode_128( self.read_varint_128( )? ) )
+    }
+  }
+
+  #[inline]
+  fn read_u128( &mut self ) -> Result< u128, Error > {
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => {
+        let xs = self.read_array::< 16 >( )?;
+        Ok( self.config.byte_order.decode_u128( xs ) )
+      },
+      IntEncoding::Varint => self.read_varint_128( )
+    }
+  }
+
+  #[inline]
+  fn read_u16( &mut self ) -> Result< u16, Error > {
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => self.read_fixed_u16( ),
+      IntEncoding::Varint => Ok( self.read_varint( u16::MAX as u64 )? as u16 )
+    }
+  }
+
+  #[inline]
+  fn read_u32( &mut self ) -> Result< u32, Error > {
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => self.read_fixed_u32( ),
+      IntEncoding::Varint => Ok( self.read_varint( u32::MAX as u64 )? as u32 )
+    }
+  }
+
+  #[inline]
+  fn read_u64( &mut self ) -> Result< u64, Error > {
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => self.read_fixed_u64( ),
+      IntEncoding::Varint => self.read_varint( u64::MAX )
+    }
+  }
+
+  #[inline]
+  fn read_bool( &mut self ) -> Result< bool, Error > {
+    match self.read_u8( )? {
+      0 => Ok( false ),
+      1 => Ok( true ),
+      _ if self.config.lenient_bool => Ok( true ),
+      _ => Err( Error::MalformedBool )
+    }
+  }
+
+  #[inline]
+  fn read_char( &mut self ) -> Result< char, Error > {
+    if self.config.char_encoding == CharEncoding::CodePoint {
+      return char::from_u32( self.read_u32( )? ).ok_or( Error::MalformedChar );
+    }
+    let mut bytes = [0u8; 4];
+    bytes[ 0 ] = self.read_u8( )?;
+    let n = utf8_byte_len( bytes[ 0 ] ).ok_or( Error::MalformedUtf8 )?;
+    if n > 1 {
+      bytes[ 1..n ].copy_from_slice( self.read_bytes( n - 1 )?.as_slice( ) );
+    }
+    let res = str::from_utf8( &bytes[ ..n ] ).map_err( |_| Error::MalformedUtf8 )?;
+    Ok( res.chars( ).next( ).unwrap( ) )
+  }
+
+  /// Reads a string, borrowing it from the input if possible
+  #[inline]
+  fn visit_str_data< V: de::Visitor< 'de > >( &mut self, visitor: V ) -> Result< V::Value, Error > {
+    // len
+    let len = self.read_data_len( self.config.str_len )?;
+
+    // payload
+    match self.read_bytes( len )? {
+      Bytes::Borrowed( data ) => {
+        let data_str = str::from_utf8( data ).map_err( |_| Error::MalformedUtf8 )?;
+        visitor.visit_borrowed_str( data_str )
+      },
+      Bytes::Copied( data ) => {
+        let data_str = str::from_utf8( data ).map_err( |_| Error::MalformedUtf8 )?;
+        visitor.visit_str( data_str )
+      }
+    }
+  }
+
+  /// Reads a byte array, borrowing it from the input if possible
+  #[inline]
+  fn visit_bytes_data< V: de::Visitor< 'de > >( &mut self, visitor: V ) -> Result< V::Value, Error > {
+    // len
+    let len = self.read_data_len( self.config.bytes_len )?;
+
+    // payload
+    match self.read_bytes( len )? {
+      Bytes::Borrowed( data ) => visitor.visit_borrowed_bytes( data ),
+      Bytes::Copied( data ) => visitor.visit_bytes( data )
+    }
+  }
+
+  /// Deserializes a value of the self-describing encoding, as given by its
+  /// tag. Out of line, such that the default encoding is not slowed down by it.
+  #[cold]
+  fn deserialize_tagged< V: de::Visitor< 'de > >( &mut self, visitor: V ) -> Result< V::Value, Error > {
+    let tag = self.read_u8( )?;
+    self.visit_tagged( tag, visitor )
+  }
+
+  /// Deserializes a value of the self-describing encoding, whose `tag` is
+  /// already read
+  #[cold]
+  fn visit_tagged< V: de::Visitor< 'de > >( &mut self, tag: u8, visitor: V ) -> Result< V::Value, Error > {
+    match tag {
+      tag::UNIT  => visitor.visit_unit( ),
+      tag::BOOL  => visitor.visit_bool( self.read_bool( )? ),
+      tag::I8    => visitor.visit_i8( self.read_u8( )? as i8 ),
+      tag::I16   => visitor.visit_i16( self.read_i16( )? ),
+      tag::I32   => visitor.visit_i32( self.read_i32( )? ),
+      tag::I64   => visitor.visit_i64( self.read_i64( )? ),
+      tag::I128  => visitor.visit_i128( self.read_i128( )? ),
+      tag::U8    => visitor.visit_u8( self.read_u8( )? ),
+      tag::U16   => visitor.visit_u16( self.read_u16( )? ),
+      tag::U32   => visitor.visit_u32( self.read_u32( )? ),
+      tag::U64   => visitor.visit_u64( self.read_u64( )? ),
+      tag::U128  => visitor.visit_u128( self.read_u128( )? ),
+      tag::F32   => visitor.visit_f32( f32::from_bits( self.read_fixed_u32( )? ) ),
+      tag::F64   => visitor.visit_f64( f64::from_bits( self.read_fixed_u64( )? ) ),
+      tag::CHAR  => visitor.visit_char( self.read_char( )? ),
+      tag::STR   => self.visit_str_data( visitor ),
+      tag::BYTES => self.visit_bytes_data( visitor ),
+      tag::NONE  => visitor.visit_none( ),
+      tag::SOME  => {
+        self.enter( )?;
+        let res = visitor.visit_some( &mut *self );
+        self.leave( );
+        res
+      },
+      tag::SEQ   => {
+        let len = self.read_count( self.config.seq_len )?;
+        self.visit_sequenced_seq( len, None, visitor )
+      },
+      tag::MAP   => {
+        let len = self.read_count( self.config.map_len )?;
+        self.visit_sequenced_map( len, None, visitor )
+      },
+      _ => Err( Error::MalformedTag )
+    }
+  }
+
+  /// Deserializes a struct of the self-describing encoding, which is a map
+  /// keyed by the names of its `fields`
+  #[cold]
+  fn deserialize_tagged_struct< V: de::Visitor< 'de > >( &mut self, fields: &'static [&'static str], visitor: V ) -> Result< V::Value, Error > {
+    match self.read_u8( )? {
+      tag::MAP => {
+        let len = self.read_count( self.config.map_len )?;
+        self.visit_sequenced_map( len, Some( fields ), visitor )
+      },
+      tag => self.visit_tagged( tag, visitor )
+    }
+  }
+
+  /// Reads a name of the self-describing encoding into `seed`. Also returns
+  /// the equal entry of `names`, if any, which outlives the input.
+  #[cold]
+  fn read_name< S: de::DeserializeSeed< 'de > >( &mut self, names: &'static [&'static str], seed: S ) -> Result< ( S::Value, Option< &'static str > ), Error > {
+    if self.read_u8( )? != tag::STR {
+      return Err( Error::MalformedTag );
+    }
+    let len = self.read_data_len( self.config.str_len )?;
+    let find = |name: &str| names.iter( ).copied( ).find( |x| *x == name );
+    match self.read_bytes( len )? {
+      Bytes::Borrowed( data ) => {
+        let name = str::from_utf8( data ).map_err( |_| Error::MalformedUtf8 )?;
+        Ok( ( seed.deserialize( de::value::BorrowedStrDeserializer::new( name ) )?, find( name ) ) )
+      },
+      Bytes::Copied( data ) => {
+        let name = str::from_utf8( data ).map_err( |_| Error::MalformedUtf8 )?;
+        Ok( ( seed.deserialize( de::value::StrDeserializer::new( name ) )?, find( name ) ) )
+      }
+    }
+  }
+
+  /// Deserializes an enum of the self-describing encoding, which is either the
+  /// name of a unit variant, or a map from the name of a variant to its
+  /// content
+  #[cold]
+  fn deserialize_tagged_enum< V: de::Visitor< 'de > >( &mut self, variants: &'static [&'static str], visitor: V ) -> Result< V::Value, Error > {
+    match self.read_u8( )? {
+      tag::STR => {
+        let len = self.read_data_len( self.config.str_len )?;
+        match self.read_bytes( len )? {
+          Bytes::Borrowed( data ) => {
+            let name = str::from_utf8( data ).map_err( |_| Error::MalformedUtf8 )?;
+            visitor.visit_enum( de::value::BorrowedStrDeserializer::new( name ) )
+          },
+          Bytes::Copied( data ) => {
+            let name = str::from_utf8( data ).map_err( |_| Error::MalformedUtf8 )?;
+            visitor.visit_enum( de::value::StrDeserializer::new( name ) )
+          }
+        }
+      },
+      tag::MAP => {
+        if self.read_count( self.config.map_len )? != 1 {
+          return Err( Error::MalformedTag );
+        }
+        visitor.visit_enum( TaggedEnum { de: self, variants, variant: None } )
+      },
+      _ => Err( Error::MalformedTag )
+    }
   }
 }
 
-impl< 'de, 'a > de::Deserializer< 'de > for &'a mut BinDeserializer< 'de > {
+impl< 'de, R: Input< 'de > > de::Deserializer< 'de > for &mut BinDeserializer< 'de, R > {
   type Error = Error;
 
-  fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+  fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
-    // Our format is not self-describing, so there's no way to deserialize any
-    // type.
+    // Unless self-describing, our format has no way to deserialize any type
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
     Err( Error::UnknownType )
   }
 
@@ -68,73 +549,82 @@ impl< 'de, 'a > de::Deserializer< 'de > for &'a mut BinDeserializer< 'de > {
   fn deserialize_bool<V>( self, visitor: V ) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
-    
-    match self.peek_u8( )? {
-      0 => {
-        self.skip_unchecked( 1 );
-        visitor.visit_bool( false )
-      },
-      1 => {
-        self.skip_unchecked( 1 );
-        visitor.visit_bool( true )
-      },
-      _ => Err( Error::MalformedBool )
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
     }
+
+    visitor.visit_bool( self.read_bool( )? )
   }
 
   #[inline]
   fn deserialize_i8<V>( self, visitor: V ) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
-    
-    let res = self.peek_u8( )?;
-    self.skip_unchecked( 1 );
-    visitor.visit_i8( unsafe { std::mem::transmute( res ) } )
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
+
+    let res = self.read_u8( )?;
+    visitor.visit_i8( res as i8 )
   }
 
   #[inline]
   fn deserialize_i16<V>( self, visitor: V ) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
-    
-    const N: usize = size_of::< u16 >( );
-    let res = self.peek_u8_n::< N >( )?;
-    let res = u16::from_ne_bytes( unsafe { res.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
-    visitor.visit_i16( unsafe { std::mem::transmute( res ) } )
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
+
+    let res = self.read_i16( )?;
+    visitor.visit_i16( res )
   }
 
   #[inline]
   fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    const N: usize = size_of::< u32 >( );
-    let res = self.peek_u8_n::< N >( )?;
-    let res = u32::from_ne_bytes( unsafe { res.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
-    visitor.visit_i32( unsafe { std::mem::transmute( res ) } )
+    let res = self.read_i32( )?;
+    visitor.visit_i32( res )
   }
 
   #[inline]
   fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
+
+    let res = self.read_i64( )?;
+    visitor.visit_i64( res )
+  }
+
+  #[inline]
+  fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+      V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    const N: usize = size_of::< u64 >( );
-    let res = self.peek_u8_n::< N >( )?;
-    let res = u64::from_ne_bytes( unsafe { res.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
-    visitor.visit_i64( unsafe { std::mem::transmute( res ) } )
+    let res = self.read_i128( )?;
+    visitor.visit_i128( res )
   }
 
   #[inline]
   fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    let res = self.peek_u8( )?;
-    self.skip_unchecked( 1 );
+    let res = self.read_u8( )?;
     visitor.visit_u8( res )
   }
 
@@ -142,11 +632,11 @@ impl< 'de, 'a > de::Deserializer< 'de > for &'a mut BinDeserializer< 'de > {
   fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    const N: usize = size_of::< u16 >( );
-    let res = self.peek_u8_n::< N >( )?;
-    let res = u16::from_ne_bytes( unsafe { res.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
+    let res = self.read_u16( )?;
     visitor.visit_u16( res )
   }
 
@@ -154,11 +644,11 @@ impl< 'de, 'a > de::Deserializer< 'de > for &'a mut BinDeserializer< 'de > {
   fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    const N: usize = size_of::< u32 >( );
-    let res = self.peek_u8_n::< N >( )?;
-    let res = u32::from_ne_bytes( unsafe { res.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
+    let res = self.read_u32( )?;
     visitor.visit_u32( res )
   }
 
@@ -166,141 +656,153 @@ impl< 'de, 'a > de::Deserializer< 'de > for &'a mut BinDeserializer< 'de > {
   fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    const N: usize = size_of::< u64 >( );
-    let res = self.peek_u8_n::< N >( )?;
-    let res = u64::from_ne_bytes( unsafe { res.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
+    let res = self.read_u64( )?;
     visitor.visit_u64( res )
   }
 
+  #[inline]
+  fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+      V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
+
+    let res = self.read_u128( )?;
+    visitor.visit_u128( res )
+  }
+
   #[inline]
   fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
-        
-    const N: usize = size_of::< u32 >( );
-    let res = self.peek_u8_n::< N >( )?;
-    let res = u32::from_ne_bytes( unsafe { res.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
-    visitor.visit_f32( unsafe { std::mem::transmute( res ) } )
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
+
+    let res = self.read_fixed_u32( )?;
+    visitor.visit_f32( f32::from_bits( res ) )
   }
 
   #[inline]
   fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    const N: usize = size_of::< u64 >( );
-    let res = self.peek_u8_n::< N >( )?;
-    let res = u64::from_ne_bytes( unsafe { res.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
-    visitor.visit_f64( unsafe { std::mem::transmute( res ) } )
+    let res = self.read_fixed_u64( )?;
+    visitor.visit_f64( f64::from_bits( res ) )
   }
 
   #[inline]
   fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    let b0 = self.peek_u8( )?;
-    let n = utf8_byte_len( b0 ).ok_or( Error::MalformedUtf8 )?;
-    let bytes = self.peek_u8_nd( n )?;
-    let res = str::from_utf8( bytes ).map_err( |_| Error::MalformedUtf8 )?;
-    let res_char = res.chars( ).next( ).unwrap( );
-    self.skip_unchecked( n );
-    visitor.visit_char( res_char )
+    visitor.visit_char( self.read_char( )? )
   }
 
   #[inline]
   fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    // len
-    const N: usize = size_of::< u32 >( );
-    let len = self.peek_u8_n::< N >( )?;
-    let len = u32::from_ne_bytes( unsafe { len.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
-
-    // payload
-    let data = self.peek_u8_nd( len as usize )?;
-    let data_str = str::from_utf8( data ).map_err( |_| Error::MalformedUtf8 )?;
-    let res = visitor.visit_str( data_str );
-    self.skip_unchecked( len as usize );
-    res
+    self.visit_str_data( visitor )
   }
 
+  #[cfg(feature = "alloc")]
   #[inline]
   fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
     // len
-    const N: usize = size_of::< u32 >( );
-    let len = self.peek_u8_n::< N >( )?;
-    let len = u32::from_ne_bytes( unsafe { len.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
+    let len = self.read_data_len( self.config.str_len )?;
 
     // payload
-    let data = self.peek_u8_nd( len as usize )?;
-    let data_str = String::from_utf8( data.to_owned( ) ).map_err( |_| Error::MalformedUtf8 )?;
-    let res = visitor.visit_string( data_str );
-    self.skip_unchecked( len as usize );
-    res
+    let data = self.read_byte_buf( len )?;
+    let data_str = String::from_utf8( data ).map_err( |_| Error::MalformedUtf8 )?;
+    visitor.visit_string( data_str )
   }
 
+  #[cfg(not(feature = "alloc"))]
   #[inline]
-  fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+  fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    self.deserialize_str( visitor )
+  }
 
-    // len
-    const N: usize = size_of::< u64 >( );
-    let len = self.peek_u8_n::< N >( )?;
-    let len = u64::from_ne_bytes( unsafe { len.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
+  #[inline]
+  fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+      V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    // payload
-    let data = self.peek_u8_nd( len as usize )?;
-    let res = visitor.visit_bytes( data );
-    self.skip_unchecked( len as usize );
-    res
+    self.visit_bytes_data( visitor )
   }
 
+  #[cfg(feature = "alloc")]
   #[inline]
   fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
     // len
-    const N: usize = size_of::< u64 >( );
-    let len = self.peek_u8_n::< N >( )?;
-    let len = u64::from_ne_bytes( unsafe { len.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
+    let len = self.read_data_len( self.config.bytes_len )?;
 
     // payload
-    let data = self.peek_u8_nd( len as usize )?;
-    let res = visitor.visit_byte_buf( data.to_owned( ) );
-    self.skip_unchecked( len as usize );
-    res
+    let data = self.read_byte_buf( len )?;
+    visitor.visit_byte_buf( data )
+  }
+
+  #[cfg(not(feature = "alloc"))]
+  #[inline]
+  fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+      V: de::Visitor<'de> {
+    self.deserialize_bytes( visitor )
   }
 
   #[inline]
   fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    let marker = self.peek_u8( )?;
+    let marker = self.read_u8( )?;
 
     match marker {
       0 => { // None
-        self.skip_unchecked( 1 );
         visitor.visit_none( )
       },
       1 => { // Some
-        self.skip_unchecked( 1 );
-        visitor.visit_some( self )
+        self.enter( )?;
+        let res = visitor.visit_some( &mut *self );
+        self.leave( );
+        res
       },
       _ => {
         Err( Error::MalformedOption )
@@ -312,6 +814,9 @@ impl< 'de, 'a > de::Deserializer< 'de > for &'a mut BinDeserializer< 'de > {
   fn deserialize_unit< V >( self, visitor: V ) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
     visitor.visit_unit( )
   }
@@ -344,13 +849,13 @@ impl< 'de, 'a > de::Deserializer< 'de > for &'a mut BinDeserializer< 'de > {
   fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
+
+    let len = self.read_count( self.config.seq_len )?;
     
-    const N: usize = size_of::< u32 >( );
-    let res = self.peek_u8_n::< N >( )?;
-    let len = u32::from_ne_bytes( unsafe { res.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
-    
-    visitor.visit_seq( Sequenced { de: self, len: len as usize } )
+    self.visit_sequenced_seq( len, None, visitor )
   }
 
   #[inline]
@@ -361,8 +866,11 @@ impl< 'de, 'a > de::Deserializer< 'de > for &'a mut BinDeserializer< 'de > {
   ) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
-    
-    visitor.visit_seq( Sequenced { de: self, len } )
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
+
+    self.visit_sequenced_seq( len, None, visitor )
   }
 
   #[inline]
@@ -374,22 +882,25 @@ impl< 'de, 'a > de::Deserializer< 'de > for &'a mut BinDeserializer< 'de > {
   ) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    visitor.visit_seq( Sequenced { de: self, len } )
+    self.visit_sequenced_seq( len, None, visitor )
   }
 
   #[inline]
   fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
-    
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
+
     // len
-    const N: usize = size_of::< u32 >( );
-    let len = self.peek_u8_n::< N >( )?;
-    let len = u32::from_ne_bytes( unsafe { len.try_into( ).unwrap_unchecked( ) } );
-    self.skip_unchecked( N );
+    let len = self.read_count( self.config.map_len )?;
 
-    visitor.visit_map( Sequenced { de: self, len: len as usize } )
+    self.visit_sequenced_map( len, None, visitor )
   }
 
   #[inline]
@@ -401,29 +912,40 @@ impl< 'de, 'a > de::Deserializer< 'de > for &'a mut BinDeserializer< 'de > {
   ) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
-    
-    visitor.visit_seq( Sequenced { de: self, len: fields.len( ) } )
+    // When self-describing, structs are maps keyed by field name
+    if self.config.self_describing {
+      return self.deserialize_tagged_struct( fields, visitor );
+    }
+
+    self.visit_sequenced_seq( fields.len( ), Some( fields ), visitor )
   }
 
   #[inline]
   fn deserialize_enum<V>(
     self
   , _name: &'static str
-  , _variants: &'static [&'static str]
+  , variants: &'static [&'static str]
   , visitor: V
   ) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
 
-    visitor.visit_enum( Enum { de: self } )
+    if self.config.self_describing {
+      return self.deserialize_tagged_enum( variants, visitor );
+    }
+    visitor.visit_enum( Enum { de: self, variants, variant: 0 } )
   }
 
   #[inline]
   fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
+    if self.config.self_describing {
+      return self.deserialize_tagged( visitor );
+    }
 
-    self.deserialize_u8( visitor )
+    let idx = self.read_int( self.config.variant_width )?;
+    visitor.visit_u64( idx )
   }
 
   #[inline]
@@ -432,14 +954,104 @@ impl< 'de, 'a > de::Deserializer< 'de > for &'a mut BinDeserializer< 'de > {
       V: de::Visitor<'de> {
     self.deserialize_any( visitor )
   }
+
+  #[inline]
+  fn is_human_readable( &self ) -> bool {
+    self.config.human_readable
+  }
+}
+
+struct Sequenced< 'a, 'de: 'a, R: Input< 'de > > {
+  de: &'a mut BinDeserializer< 'de, R >,
+  /// Number of remaining elements (or entries). An entry counts as remaining
+  /// until its value is deserialized.
+  len: usize,
+  /// Field names, if the map is a struct of the self-describing encoding
+  fields: Option< &'static [&'static str] >,
+  /// Field of the current entry, once its key is read, if it is known
+  field: Option< &'static str >,
+  /// Encodings of the map keys visited so far. Only tracked in strict mode.
+  #[cfg(feature = "alloc")]
+  keys: BTreeSet< Cow< 'de, [u8] > >
 }
 
-struct Sequenced< 'a, 'de: 'a > {
-  de: &'a mut BinDeserializer< 'de >,
-  len: usize
+impl< 'a, 'de, R: Input< 'de > > Sequenced< 'a, 'de, R > {
+  #[inline]
+  fn new( de: &'a mut BinDeserializer< 'de, R >, len: usize ) -> Self {
+    Sequenced {
+      de,
+      len,
+      fields: None,
+      field: None,
+      #[cfg(feature = "alloc")]
+      keys: BTreeSet::new( )
+    }
+  }
+}
+
+impl< 'de, R: Input< 'de > > BinDeserializer< 'de, R > {
+  /// Visits `len` elements as a sequence, which is a struct if `fields` is
+  /// given. In strict mode, the visitor must consume all of them.
+  #[inline]
+  fn visit_sequenced_seq< V: de::Visitor< 'de > >( &mut self, len: usize, fields: Option< &'static [&'static str] >, visitor: V ) -> Result< V::Value, Error > {
+    self.enter( )?;
+    let mut seq = Sequenced::new( self, len );
+    let res = visitor.visit_seq( &mut seq );
+    let rest = seq.len;
+    self.leave( );
+    let res = match res {
+      Ok( res ) => res,
+      Err( err ) => return Err( self.locate_element( err, len - rest, len, fields ) )
+    };
+    if rest > 0 && self.config.strict {
+      return Err( Error::UnconsumedElements );
+    }
+    Ok( res )
+  }
+
+  /// Visits `len` entries as a map, which is a struct keyed by the names of
+  /// `fields` if given. In strict mode, the visitor must consume all of them.
+  #[inline]
+  fn visit_sequenced_map< V: de::Visitor< 'de > >( &mut self, len: usize, fields: Option< &'static [&'static str] >, visitor: V ) -> Result< V::Value, Error > {
+    self.enter( )?;
+    let mut map = Sequenced::new( self, len );
+    map.fields = fields;
+    let res = visitor.visit_map( &mut map );
+    let ( rest, field ) = ( map.len, map.field );
+    self.leave( );
+    let res = match res {
+      Ok( res ) => res,
+      Err( err ) => {
+        let index = len - rest;
+        return Err( match field {
+          Some( field ) if index < len => self.locate( err, Some( Segment::Field( field ) ) ),
+          _ => self.locate_element( err, index, len, None )
+        } );
+      }
+    };
+    if rest > 0 && self.config.strict {
+      return Err( Error::UnconsumedElements );
+    }
+    Ok( res )
+  }
+
+  /// Records that `err` occurred within the element at `index`, which is the
+  /// first element not yet deserialized. Only the visitor itself fails beyond
+  /// the last element.
+  #[cold]
+  fn locate_element( &self, err: Error, index: usize, len: usize, fields: Option< &'static [&'static str] > ) -> Error {
+    if index >= len {
+      return self.locate( err, None );
+    }
+    let segment = match fields.and_then( |fields| fields.get( index ) ) {
+      Some( field ) => Segment::Field( field ),
+      None => Segment::Index( index )
+    };
+    self.locate( err, Some( segment ) )
+  }
 }
 
-impl< 'de, 'a > SeqAccess< 'de > for Sequenced< 'a, 'de > {
+impl< 'de, R: Input< 'de > > SeqAccess< 'de > for Sequenced< '_, 'de, R > {
   type Error = Error;
 
   #[inline]
@@ -455,9 +1067,27 @@ impl< 'de, 'a > SeqAccess< 'de > for Sequenced< 'a, 'de > {
       Ok( Some( val ) )
     }
   }
+
+  #[inline]
+  fn size_hint( &self ) -> Option< usize > {
+    Some( self.len.min( self.de.config.limits.max_size_hint ) )
+  }
+}
+
+impl< 'de, R: Input< 'de > > Sequenced< '_, 'de, R > {
+  /// Deserializes a map key. Field names are recorded for error paths.
+  #[inline]
+  fn read_key< K: de::DeserializeSeed< 'de > >( &mut self, seed: K ) -> Result< K::Value, Error > {
+    let Some( fields ) = self.fields else {
+      return seed.deserialize( &mut *self.de );
+    };
+    let ( val, field ) = self.de.read_name( fields, seed )?;
+    self.field = field;
+    Ok( val )
+  }
 }
 
-impl< 'de, 'a > MapAccess< 'de > for Sequenced< 'a, 'de > {
+impl< 'de, R: Input< 'de > > MapAccess< 'de > for Sequenced< '_, 'de, R > {
   type Error = Error;
 
   #[inline]
@@ -466,12 +1096,22 @@ impl< 'de, 'a > MapAccess< 'de > for Sequenced< 'a, 'de > {
       K: de::DeserializeSeed<'de> {
     
     if self.len == 0 {
-      Ok( None )
-    } else {
-      let val = seed.deserialize( &mut *self.de )?;
-      self.len -= 1;
-      Ok( Some( val ) )
+      return Ok( None );
     }
+    self.field = None;
+    // Without `alloc`, keys cannot be stored, so duplicates go undetected
+    #[cfg(feature = "alloc")]
+    if self.de.config.strict {
+      let start = self.de.input.begin_capture( );
+      let val = self.read_key( seed )?;
+      // Equal keys have equal encodings
+      let key = self.de.input.end_capture( start );
+      if !self.keys.insert( key ) {
+        return Err( Error::DuplicateMapKey );
+      }
+      return Ok( Some( val ) );
+    }
+    Ok( Some( self.read_key( seed )? ) )
   }
 
   #[inline]
@@ -479,30 +1119,54 @@ impl< 'de, 'a > MapAccess< 'de > for Sequenced< 'a, 'de > {
     where
       V: de::DeserializeSeed<'de> {
 
-    seed.deserialize( &mut *self.de )
+    let val = seed.deserialize( &mut *self.de )?;
+    self.len = self.len.saturating_sub( 1 );
+    Ok( val )
+  }
+
+  #[inline]
+  fn size_hint( &self ) -> Option< usize > {
+    Some( self.len.min( self.de.config.limits.max_size_hint ) )
   }
 }
 
 
-struct Enum< 'a, 'de: 'a > {
-  de: &'a mut BinDeserializer< 'de >
+struct Enum< 'a, 'de: 'a, R: Input< 'de > > {
+  de: &'a mut BinDeserializer< 'de, R >,
+  variants: &'static [&'static str],
+  /// Index of the variant, once it is read
+  variant: usize
+}
+
+impl< 'de, R: Input< 'de > > Enum< '_, 'de, R > {
+  /// Records that `err` occurred within the content of the variant
+  #[cold]
+  fn locate( &self, err: Error ) -> Error {
+    self.de.locate( err, Some( Segment::Variant( self.variants[ self.variant ] ) ) )
+  }
 }
 
-impl< 'a, 'de > EnumAccess< 'de > for Enum< 'a, 'de > {
+impl< 'de, R: Input< 'de > > EnumAccess< 'de > for Enum< '_, 'de, R > {
   type Error = Error;
 
   type Variant = Self;
 
   #[inline]
-  fn variant_seed<V>( self, seed: V ) -> Result<(V::Value, Self::Variant), Self::Error>
+  fn variant_seed<V>( mut self, seed: V ) -> Result<(V::Value, Self::Variant), Self::Error>
     where
       V: de::DeserializeSeed<'de> {
-    let idx = seed.deserialize( &mut *self.de )?;
-    Ok( ( idx, self ) )
+    let idx = self.de.read_int( self.de.config.variant_width )?;
+    if idx >= self.variants.len( ) as u64 {
+      return Err( Error::UnknownEnumVariant );
+    }
+    self.variant = idx as usize;
+    let idx: de::value::U32Deserializer< Error > = ( idx as u32 ).into_deserializer( );
+    let val = seed.deserialize( idx )?;
+    Ok( ( val, self ) )
   }
 }
 
-impl<'de, 'a> VariantAccess<'de> for Enum<'a, 'de> {
+impl< 'de, R: Input< 'de > > VariantAccess< 'de > for Enum< '_, 'de, R > {
   type Error = Error;
 
   #[inline]
@@ -515,7 +1179,11 @@ impl<'de, 'a> VariantAccess<'de> for Enum<'a, 'de> {
     where
       T: de::DeserializeSeed<'de> {
 
-    seed.deserialize( self.de )
+    // Tuple and struct variants are entered as tuples
+    self.de.enter( )?;
+    let res = seed.deserialize( &mut *self.de );
+    self.de.leave( );
+    res.map_err( |err| self.locate( err ) )
   }
 
   #[inline]
@@ -523,19 +1191,106 @@ impl<'de, 'a> VariantAccess<'de> for Enum<'a, 'de> {
     where
       V: de::Visitor<'de> {
     
-    self.de.deserialize_tuple( len, visitor )
+    match self.de.deserialize_tuple( len, visitor ) {
+      Ok( res ) => Ok( res ),
+      Err( err ) => Err( self.locate( err ) )
+    }
   }
 
   #[inline]
   fn struct_variant<V>(
     self
-  , _fields: &'static [&'static str]
+  , fields: &'static [&'static str]
   , visitor: V
   ) -> Result<V::Value, Self::Error>
     where
       V: de::Visitor<'de> {
-    
-    self.de.deserialize_map( visitor )
+
+    // Like structs, struct variants are serialized without a length prefix
+    match self.de.visit_sequenced_seq( fields.len( ), Some( fields ), visitor ) {
+      Ok( res ) => Ok( res ),
+      Err( err ) => Err( self.locate( err ) )
+    }
+  }
+}
+
+/// Enum of the self-describing encoding, as a map with a single entry from the
+/// name of its variant to its content
+struct TaggedEnum< 'a, 'de: 'a, R: Input< 'de > > {
+  de: &'a mut BinDeserializer< 'de, R >,
+  variants: &'static [&'static str],
+  /// Name of the variant, once it is read, if it is known
+  variant: Option< &'static str >
+}
+
+impl< 'de, R: Input< 'de > > TaggedEnum< '_, 'de, R > {
+  /// Records that `err` occurred within the content of the variant
+  #[cold]
+  fn locate( &self, err: Error ) -> Error {
+    self.de.locate( err, self.variant.map( Segment::Variant ) )
+  }
+}
+
+impl< 'de, R: Input< 'de > > EnumAccess< 'de > for TaggedEnum< '_, 'de, R > {
+  type Error = Error;
+
+  type Variant = Self;
+
+  #[inline]
+  fn variant_seed<V>( mut self, seed: V ) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+      V: de::DeserializeSeed<'de> {
+    let ( val, variant ) = self.de.read_name( self.variants, seed )?;
+    self.variant = variant;
+    Ok( ( val, self ) )
+  }
+}
+
+impl< 'de, R: Input< 'de > > VariantAccess< 'de > for TaggedEnum< '_, 'de, R > {
+  type Error = Error;
+
+  #[inline]
+  fn unit_variant(self) -> Result<(), Self::Error> {
+    match de::Deserialize::deserialize( &mut *self.de ) {
+      Ok( res ) => Ok( res ),
+      Err( err ) => Err( self.locate( err ) )
+    }
+  }
+
+  #[inline]
+  fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+      T: de::DeserializeSeed<'de> {
+
+    // Tuple and struct variants are entered as sequences and maps
+    self.de.enter( )?;
+    let res = seed.deserialize( &mut *self.de );
+    self.de.leave( );
+    res.map_err( |err| self.locate( err ) )
+  }
+
+  #[inline]
+  fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+      V: de::Visitor<'de> {
+    match self.de.deserialize_tagged( visitor ) {
+      Ok( res ) => Ok( res ),
+      Err( err ) => Err( self.locate( err ) )
+    }
+  }
+
+  #[inline]
+  fn struct_variant<V>(
+    self
+  , fields: &'static [&'static str]
+  , visitor: V
+  ) -> Result<V::Value, Self::Error>
+    where
+      V: de::Visitor<'de> {
+    match self.de.deserialize_tagged_struct( fields, visitor ) {
+      Ok( res ) => Ok( res ),
+      Err( err ) => Err( self.locate( err ) )
+    }
   }
 }
 
@@ -567,23 +1322,98 @@ fn utf8_byte_len( b0: u8 ) -> Option< usize > {
 /// differently.
 #[derive(Debug)]
 pub enum Error {
+  #[cfg(feature = "alloc")]
   Custom( String ),
+  /// Without `alloc`, custom error messages cannot be stored
+  #[cfg(not(feature = "alloc"))]
+  Custom,
   UnknownType,
   MalformedBool,
   MalformedUtf8,
   MalformedOption,
   InsufficientData,
+  /// The `std::io::Read` ended before the value was complete
+  UnexpectedEof,
+  /// Reading from the underlying `std::io::Read` failed
+  #[cfg(feature = "std")]
+  Io( io::Error ),
   UnknownSeqLen,
-  UnknownEnumVariant
+  UnknownEnumVariant,
+  /// A varint is overlong, or its value does not fit in the target type
+  MalformedVarint,
+  /// (Strict mode) Input remains after the value was deserialized
+  TrailingBytes,
+  /// (Strict mode) A sequence or map contains more elements than its visitor
+  /// consumed
+  UnconsumedElements,
+  /// (Strict mode) A map contains the same key twice
+  DuplicateMapKey,
+  /// The input exceeds one of the configured `Limits`
+  LimitExceeded( Limit ),
+  /// The value is nested deeper than `Config::max_depth`
+  DepthLimitExceeded,
+  /// (Self-describing) A type tag is unknown, or an enum is neither a variant
+  /// name nor a map with a single entry
+  MalformedTag,
+  /// (With `CharEncoding::CodePoint`) A code point is not a Unicode scalar
+  /// value
+  MalformedChar,
+  /// (With `Config::envelope`) The input does not start with the magic bytes
+  /// of an envelope
+  UnknownMagic,
+  /// (With `Config::envelope`) The envelope has a format version that this
+  /// version of the crate does not support
+  UnsupportedVersion( u8 ),
+  /// (With `Config::envelope`) The envelope contains invalid settings
+  MalformedEnvelope,
+  /// (Typed) The fingerprint of the input is not that of the target type
+  TypeMismatch,
+  /// (Typed) The shape of the type cannot be traced, as it rejects every
+  /// placeholder value
+  UntraceableType,
+  /// (With `Config::checksum`) The data does not match its checksum
+  ChecksumMismatch,
+  /// (`FrameReader`) A frame is longer than the maximum frame length
+  FrameTooLarge,
+  /// (With `Config::error_context`) An error, with where it occurred
+  #[cfg(feature = "alloc")]
+  Context( Box< Context > )
+}
+
+/// Where a deserialization error occurred
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct Context {
+  /// Number of bytes read successfully before the error
+  pub offset: u64,
+  pub path: Path,
+  pub error: Error
+}
+
+#[cfg(feature = "alloc")]
+impl Error {
+  /// The error itself, without its `Context`
+  #[inline]
+  pub fn inner( &self ) -> &Error {
+    match self {
+      Error::Context( context ) => &context.error,
+      err => err
+    }
+  }
 }
 
 impl fmt::Display for Error {
   #[inline]
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
+      #[cfg(feature = "alloc")]
       Error::Custom( msg ) => {
         write!( f, "{}", msg )
       },
+      #[cfg(not(feature = "alloc"))]
+      Error::Custom => {
+        write!( f, "Custom" )
+      },
       Error::UnknownType => {
         write!( f, "UnknownType" )
       },
@@ -599,21 +1429,102 @@ impl fmt::Display for Error {
       Error::InsufficientData => {
         write!( f, "InsufficientData" )
       },
+      Error::UnexpectedEof => {
+        write!( f, "UnexpectedEof" )
+      },
+      #[cfg(feature = "std")]
+      Error::Io( err ) => {
+        write!( f, "Io: {}", err )
+      },
       Error::UnknownSeqLen => {
         write!( f, "UnknownSeqLen" )
       },
       Error::UnknownEnumVariant => {
         write!( f, "UnknownEnumVariant" )
+      },
+      Error::MalformedVarint => {
+        write!( f, "MalformedVarint" )
+      },
+      Error::TrailingBytes => {
+        write!( f, "TrailingBytes" )
+      },
+      Error::UnconsumedElements => {
+        write!( f, "UnconsumedElements" )
+      },
+      Error::DuplicateMapKey => {
+        write!( f, "DuplicateMapKey" )
+      },
+      Error::LimitExceeded( limit ) => {
+        write!( f, "LimitExceeded: {:?}", limit )
+      },
+      Error::DepthLimitExceeded => {
+        write!( f, "DepthLimitExceeded" )
+      },
+      Error::MalformedTag => {
+        write!( f, "MalformedTag" )
+      },
+      Error::MalformedChar => {
+        write!( f, "MalformedChar" )
+      },
+      Error::UnknownMagic => {
+        write!( f, "UnknownMagic" )
+      },
+      Error::UnsupportedVersion( version ) => {
+        write!( f, "UnsupportedVersion: {}", version )
+      },
+      Error::MalformedEnvelope => {
+        write!( f, "MalformedEnvelope" )
+      },
+      Error::TypeMismatch => {
+        write!( f, "TypeMismatch" )
+      },
+      Error::UntraceableType => {
+        write!( f, "UntraceableType" )
+      },
+      Error::ChecksumMismatch => {
+        write!( f, "ChecksumMismatch" )
+      },
+      Error::FrameTooLarge => {
+        write!( f, "FrameTooLarge" )
+      },
+      #[cfg(feature = "alloc")]
+      Error::Context( context ) => {
+        if context.path.is_empty( ) {
+          write!( f, "{} at offset {}", context.error, context.offset )
+        } else {
+          write!( f, "{} at {} (offset {})", context.error, context.path, context.offset )
+        }
       }
     }
   }
 }
 
-impl std::error::Error for Error { }
+/// With `std`, this is `std::error::Error`
+impl core::error::Error for Error {
+  #[inline]
+  fn source( &self ) -> Option< &( dyn core::error::Error + 'static ) > {
+    match self {
+      #[cfg(feature = "std")]
+      Error::Io( err ) => Some( err ),
+      #[cfg(feature = "alloc")]
+      Error::Context( context ) => context.error.source( ),
+      _ => None
+    }
+  }
+}
 
+#[cfg(feature = "alloc")]
 impl de::Error for Error {
   #[inline]
   fn custom<T>( msg: T ) -> Self where T: fmt::Display {
     Error::Custom( msg.to_string( ) )
   }
 }
+
+#[cfg(not(feature = "alloc"))]
+impl de::Error for Error {
+  #[inline]
+  fn custom<T>( _msg: T ) -> Self where T: fmt::Display {
+    Error::Custom
+  }
+}
@@ -0,0 +1,113 @@
+//! The envelope header (see `Config::envelope`)
+//!
+//! The header consists of the following, one byte each unless noted:
+//! - `MAGIC` (4 bytes)
+//! - The format `VERSION`
+//! - `byte_order`: 0 little, 1 big. The native byte order is recorded as that
+//!   of the writing machine.
+//! - `int_encoding`: 0 fixed, 1 varint
+//! - `str_len`, `bytes_len`, `seq_len`, `map_len` and `variant_width`: 0 `U8`,
+//!   1 `U16`, 2 `U32`, 3 `U64`, 4 varint
+//! - `char_encoding`: 0 UTF-8, 1 code point
+//! - Flags: bit 0 `self_describing`, bit 1 `human_readable`, bit 2 `checksum`
+
+// local imports
+use crate::config::{ByteOrder, CharEncoding, Config, IntEncoding, IntWidth};
+use crate::deserialize::Error;
+
+
+pub(crate) const MAGIC: [u8; 4] = *b"BSER";
+
+/// Incremented whenever the encoding changes incompatibly
+pub(crate) const VERSION: u8 = 1;
+
+pub(crate) const LEN: usize = 14;
+
+const SELF_DESCRIBING: u8 = 0b01;
+const HUMAN_READABLE: u8 = 0b10;
+const CHECKSUM: u8 = 0b100;
+
+/// Encodes the header of data encoded with `config`
+pub(crate) fn encode( config: &Config ) -> [u8; LEN] {
+  let mut header = [0u8; LEN];
+  header[ ..4 ].copy_from_slice( &MAGIC );
+  header[ 4 ] = VERSION;
+  header[ 5 ] = match config.byte_order {
+    ByteOrder::Little => 0,
+    ByteOrder::Big    => 1,
+    ByteOrder::Native => if cfg!( target_endian = "big" ) { 1 } else { 0 }
+  };
+  header[ 6 ] = match config.int_encoding {
+    IntEncoding::Fixed  => 0,
+    IntEncoding::Varint => 1
+  };
+  let widths = [ config.str_len, config.bytes_len, config.seq_len, config.map_len, config.variant_width ];
+  for ( x, width ) in header[ 7..12 ].iter_mut( ).zip( widths ) {
+    *x = match width {
+      IntWidth::U8     => 0,
+      IntWidth::U16    => 1,
+      IntWidth::U32    => 2,
+      IntWidth::U64    => 3,
+      IntWidth::Varint => 4
+    };
+  }
+  header[ 12 ] = match config.char_encoding {
+    CharEncoding::Utf8      => 0,
+    CharEncoding::CodePoint => 1
+  };
+  if config.self_describing {
+    header[ 13 ] |= SELF_DESCRIBING;
+  }
+  if config.human_readable {
+    header[ 13 ] |= HUMAN_READABLE;
+  }
+  if config.checksum {
+    header[ 13 ] |= CHECKSUM;
+  }
+  header
+}
+
+/// Decodes a header. Returns `config`, with the encoding settings replaced by
+/// those of the header.
+pub(crate) fn decode( header: [u8; LEN], mut config: Config ) -> Result< Config, Error > {
+  if header[ ..4 ] != MAGIC {
+    return Err( Error::UnknownMagic );
+  }
+  if header[ 4 ] != VERSION {
+    return Err( Error::UnsupportedVersion( header[ 4 ] ) );
+  }
+  config.byte_order = match header[ 5 ] {
+    0 => ByteOrder::Little,
+    1 => ByteOrder::Big,
+    _ => return Err( Error::MalformedEnvelope )
+  };
+  config.int_encoding = match header[ 6 ] {
+    0 => IntEncoding::Fixed,
+    1 => IntEncoding::Varint,
+    _ => return Err( Error::MalformedEnvelope )
+  };
+  let mut widths = [IntWidth::U8; 5];
+  for ( width, x ) in widths.iter_mut( ).zip( &header[ 7..12 ] ) {
+    *width = match x {
+      0 => IntWidth::U8,
+      1 => IntWidth::U16,
+      2 => IntWidth::U32,
+      3 => IntWidth::U64,
+      4 => IntWidth::Varint,
+      _ => return Err( Error::MalformedEnvelope )
+    };
+  }
+  [ config.str_len, config.bytes_len, config.seq_len, config.map_len, config.variant_width ] = widths;
+  config.char_encoding = match header[ 12 ] {
+    0 => CharEncoding::Utf8,
+    1 => CharEncoding::CodePoint,
+    _ => return Err( Error::MalformedEnvelope )
+  };
+  if header[ 13 ] & !( SELF_DESCRIBING | HUMAN_READABLE | CHECKSUM ) != 0 {
+    return Err( Error::MalformedEnvelope );
+  }
+  config.self_describing = header[ 13 ] & SELF_DESCRIBING != 0;
+  config.human_readable = header[ 13 ] & HUMAN_READABLE != 0;
+  config.checksum = header[ 13 ] & CHECKSUM != 0;
+  Ok( config )
+}
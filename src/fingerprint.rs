@@ -0,0 +1,595 @@
+//! Type fingerprints (see `crate::fingerprint`)
+//!
+//! A type's fingerprint is a hash of its serde shape, which is traced by
+//! deserializing it from a `Tracer`. The tracer produces placeholder values,
+//! while recording the primitive kinds, field names and variant names that
+//! the type's `Deserialize` implementation asks for. Only one variant of an
+//! enum can be traced per deserialization, so the type is traced repeatedly,
+//! until all variants of its enums are explored.
+//!
+//! Structs and enums are recorded as named types, which are referenced by
+//! their index of first appearance. Their names are excluded, as they do not
+//! affect the encoding. While a named type is traced within itself, nothing is
+//! recorded, and placeholder collections are empty, such that recursive types
+//! are traced in finite time.
+//!
+//! Types may reject placeholder values (e.g., `NonZeroU32` rejects 0). A
+//! deserialization that fails is retried with the next placeholder for the
+//! primitive visited last, which is the most likely to have been rejected, or
+//! once its placeholders are exhausted, for the one visited before it. Options
+//! fall back to `None`, and types deserialized with `deserialize_any` are
+//! offered placeholders of several kinds in turn.
+
+// stdlib imports
+use core::fmt::Write;
+#[cfg(feature = "std")]
+use core::any::TypeId;
+#[cfg(feature = "std")]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::sync::{PoisonError, RwLock};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+// external library imports
+use serde::de::{self, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor};
+// local imports
+use crate::config::Config;
+use crate::deserialize::Error;
+use crate::ser;
+
+
+/// Traces the shape of `T`, and hashes it
+pub(crate) fn fingerprint< 'de, T: de::Deserialize< 'de > >( config: Config ) -> Result< u64, Error > {
+  let mut tracer = Tracer {
+    human_readable: config.human_readable,
+    max_depth: config.max_depth,
+    stack: Vec::new( ),
+    reentered: 0,
+    bufs: Vec::new( ),
+    types: Vec::new( ),
+    root: None,
+    leaves: Vec::new( ),
+    choices: Vec::new( )
+  };
+  loop {
+    let explored = tracer.explored( );
+    tracer.choices.clear( );
+    let mut attempts = 0;
+    loop {
+      tracer.bufs = vec![ Shape::default( ) ];
+      tracer.leaves.clear( );
+      match T::deserialize( &mut tracer ) {
+        Ok( _ ) => break,
+        Err( Error::DepthLimitExceeded ) => return Err( Error::DepthLimitExceeded ),
+        Err( _ ) if attempts == MAX_ATTEMPTS => return Err( Error::UntraceableType ),
+        Err( _ ) => tracer.next_choice( )?
+      }
+      attempts += 1;
+    }
+    let root = tracer.bufs.pop( ).unwrap_or_default( );
+    tracer.root.get_or_insert( root );
+    // A pass that explores nothing new would be repeated identically
+    if !tracer.is_incomplete( ) || tracer.explored( ) == explored {
+      break;
+    }
+  }
+  Ok( fnv1a( tracer.canonical( ).as_bytes( ) ) )
+}
+
+/// Bounds the deserializations per pass, as backtracking over the choices of
+/// placeholders may take exponential time
+const MAX_ATTEMPTS: usize = 1 << 12;
+
+/// `fingerprint`, which is traced once per type and process
+#[cfg(feature = "std")]
+pub(crate) fn cached< T: for< 'de > de::Deserialize< 'de > + 'static >( config: Config ) -> Result< u64, Error > {
+  // Only `is_human_readable` affects the shape
+  static CACHE: RwLock< BTreeMap< ( TypeId, bool ), u64 > > = RwLock::new( BTreeMap::new( ) );
+  let key = ( TypeId::of::< T >( ), config.human_readable );
+  if let Some( &res ) = CACHE.read( ).unwrap_or_else( PoisonError::into_inner ).get( &key ) {
+    return Ok( res );
+  }
+  let res = fingerprint::< T >( config )?;
+  CACHE.write( ).unwrap_or_else( PoisonError::into_inner ).insert( key, res );
+  Ok( res )
+}
+
+/// Without `std`, there is no lock to guard a cache
+#[cfg(not(feature = "std"))]
+#[inline]
+pub(crate) fn cached< T: for< 'de > de::Deserialize< 'de > + 'static >( config: Config ) -> Result< u64, Error > {
+  fingerprint::< T >( config )
+}
+
+/// Reports a failed trace as a serialization error
+pub(crate) fn ser_error( err: Error ) -> ser::Error {
+  match err {
+    Error::DepthLimitExceeded => ser::Error::DepthLimitExceeded,
+    _ => ser::Error::UntraceableType
+  }
+}
+
+/// 64-bit FNV-1a, which is stable across platforms and versions
+fn fnv1a( xs: &[u8] ) -> u64 {
+  let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+  for &x in xs {
+    hash ^= x as u64;
+    hash = hash.wrapping_mul( 0x0100_0000_01b3 );
+  }
+  hash
+}
+
+/// A recorded shape, and the named types it references
+#[derive(Clone, Default)]
+struct Shape {
+  text: String,
+  refs: Vec< usize >
+}
+
+/// Identifies a named type. Names are not unique, so the field or variant
+/// names are included.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Key {
+  kind: &'static str,
+  name: &'static str,
+  names: &'static [&'static str],
+  len: usize
+}
+
+enum Def {
+  /// A struct, tuple struct or newtype struct, once traced
+  Struct( Option< Shape > ),
+  /// The content of each variant, once traced
+  Enum( Vec< Option< Shape > > )
+}
+
+struct Tracer {
+  human_readable: bool,
+  max_depth: usize,
+  /// Named types currently being traced, outermost first
+  stack: Vec< Key >,
+  /// Number of named types on `stack` that are traced within themselves.
+  /// Nothing is recorded while positive.
+  reentered: usize,
+  /// Shapes being recorded, innermost last
+  bufs: Vec< Shape >,
+  /// Named types, in the order of their first appearance
+  types: Vec< ( Key, Def ) >,
+  /// Shape of the traced type itself
+  root: Option< Shape >,
+  /// Number of placeholders of each primitive visited in this
+  /// deserialization, in order
+  leaves: Vec< usize >,
+  /// Placeholders chosen for primitives that rejected the first ones, by the
+  /// order in which they are visited
+  choices: Vec< ( usize, usize ) >
+}
+
+impl Tracer {
+  #[inline]
+  fn recording( &self ) -> bool {
+    self.reentered == 0
+  }
+
+  fn write( &mut self, s: &str ) {
+    if self.recording( ) {
+      if let Some( buf ) = self.bufs.last_mut( ) {
+        buf.text.push_str( s );
+      }
+    }
+  }
+
+  fn write_ref( &mut self, index: usize ) {
+    if self.recording( ) {
+      if let Some( buf ) = self.bufs.last_mut( ) {
+        let _ = write!( buf.text, "#{}", index );
+        buf.refs.push( index );
+      }
+    }
+  }
+
+  /// Visits a primitive with `candidates` placeholders. Returns the one to
+  /// produce.
+  fn leaf( &mut self, candidates: usize ) -> usize {
+    let leaf = self.leaves.len( );
+    self.leaves.push( candidates );
+    self.leaf_choice( leaf )
+  }
+
+  /// After a failed deserialization, chooses the next placeholder for the
+  /// primitive visited last that has any left. The primitives visited after it
+  /// start over from their first placeholders.
+  fn next_choice( &mut self ) -> Result< (), Error > {
+    while let Some( candidates ) = self.leaves.pop( ) {
+      let leaf = self.leaves.len( );
+      let choice = self.leaf_choice( leaf ) + 1;
+      self.choices.retain( |( l, _ )| *l < leaf );
+      if choice < candidates {
+        self.choices.push( ( leaf, choice ) );
+        return Ok( () );
+      }
+    }
+    Err( Error::UntraceableType )
+  }
+
+  fn leaf_choice( &self, leaf: usize ) -> usize {
+    self.choices.iter( ).find( |( l, _ )| *l == leaf ).map_or( 0, |( _, c )| *c )
+  }
+
+  /// Number of traced structs and variants
+  fn explored( &self ) -> usize {
+    self.types.iter( ).map( |( _, def )| match def {
+      Def::Struct( shape ) => shape.is_some( ) as usize,
+      Def::Enum( variants ) => variants.iter( ).filter( |v| v.is_some( ) ).count( )
+    } ).sum( )
+  }
+
+  fn is_incomplete( &self ) -> bool {
+    self.types.iter( ).any( |( _, def )| is_incomplete( def ) )
+  }
+
+  /// Enters a named type, and references it from the current shape. Returns
+  /// its index, unless first seen while not recording.
+  fn enter( &mut self, key: Key, def: impl FnOnce( ) -> Def ) -> Result< Option< usize >, Error > {
+    if self.stack.len( ) >= self.max_depth {
+      return Err( Error::DepthLimitExceeded );
+    }
+    let mut index = self.types.iter( ).position( |( k, _ )| *k == key );
+    if index.is_none( ) && self.recording( ) {
+      self.types.push( ( key, def( ) ) );
+      index = Some( self.types.len( ) - 1 );
+    }
+    if let Some( index ) = index {
+      self.write_ref( index );
+    }
+    if self.stack.contains( &key ) {
+      self.reentered += 1;
+    }
+    self.stack.push( key );
+    self.bufs.push( Shape::default( ) );
+    Ok( index )
+  }
+
+  /// Leaves the named type entered last. Returns its shape, if recorded.
+  fn leave( &mut self ) -> Option< Shape > {
+    let shape = self.bufs.pop( ).unwrap_or_default( );
+    if let Some( key ) = self.stack.pop( ) {
+      if self.stack.contains( &key ) {
+        self.reentered -= 1;
+        return None;
+      }
+    }
+    if self.recording( ) { Some( shape ) } else { None }
+  }
+
+  /// Traces the body of a struct-like named type
+  fn trace_struct< V >( &mut self, key: Key, body: impl FnOnce( &mut Self ) -> Result< V, Error > ) -> Result< V, Error > {
+    let index = self.enter( key, || Def::Struct( None ) )?;
+    let res = body( self );
+    let shape = self.leave( );
+    let val = res?;
+    if let ( Some( index ), Some( shape ) ) = ( index, shape ) {
+      if let Def::Struct( def @ None ) = &mut self.types[ index ].1 {
+        *def = Some( shape );
+      }
+    }
+    Ok( val )
+  }
+
+  /// Chooses the variant of the enum at `index` to trace, while recording:
+  /// one not yet traced, or else one that leads to such a variant
+  fn choose_variant( &self, index: usize ) -> usize {
+    let Def::Enum( variants ) = &self.types[ index ].1 else { return 0 };
+    if let Some( v ) = variants.iter( ).position( |v| v.is_none( ) ) {
+      return v;
+    }
+    // Types on the stack are not recorded within themselves
+    let stack: Vec< usize > = self.stack.iter( )
+      .filter_map( |key| self.types.iter( ).position( |( k, _ )| k == key ) )
+      .collect( );
+    variants.iter( ).position( |v| {
+      v.as_ref( ).is_some_and( |v| v.refs.iter( ).any( |&r| self.leads_to_incomplete( r, &mut stack.clone( ) ) ) )
+    } ).unwrap_or( 0 )
+  }
+
+  fn leads_to_incomplete( &self, index: usize, visited: &mut Vec< usize > ) -> bool {
+    if visited.contains( &index ) {
+      return false;
+    }
+    visited.push( index );
+    let def = &self.types[ index ].1;
+    if is_incomplete( def ) {
+      return true;
+    }
+    match def {
+      Def::Struct( shape ) => shape.iter( ).flat_map( |s| &s.refs ).any( |&r| self.leads_to_incomplete( r, visited ) ),
+      Def::Enum( variants ) => variants.iter( ).flatten( ).flat_map( |s| &s.refs ).any( |&r| self.leads_to_incomplete( r, visited ) )
+    }
+  }
+
+  /// The recorded shapes, as text
+  fn canonical( &self ) -> String {
+    let mut res = self.root.as_ref( ).map( |s| s.text.clone( ) ).unwrap_or_default( );
+    for ( i, ( key, def ) ) in self.types.iter( ).enumerate( ) {
+      let _ = write!( res, ";#{}=", i );
+      match def {
+        Def::Struct( shape ) => {
+          res.push_str( shape.as_ref( ).map_or( "?", |s| &s.text ) );
+        },
+        Def::Enum( variants ) => {
+          res.push_str( "enum{" );
+          for ( name, shape ) in key.names.iter( ).zip( variants ) {
+            let _ = write!( res, "{}:{},", name, shape.as_ref( ).map_or( "?", |s| &s.text ) );
+          }
+          res.push( '}' );
+        }
+      }
+    }
+    res
+  }
+
+  /// Visits a sequence of `len` placeholder elements, named by `fields` if
+  /// given
+  fn visit_elements< 'de, V: Visitor< 'de > >( &mut self, len: usize, fields: Option< &'static [&'static str] >, visitor: V ) -> Result< V::Value, Error > {
+    visitor.visit_seq( Elements { tracer: self, len, fields, index: 0 } )
+  }
+}
+
+fn is_incomplete( def: &Def ) -> bool {
+  match def {
+    Def::Struct( _ ) => false,
+    Def::Enum( variants ) => variants.iter( ).any( |v| v.is_none( ) )
+  }
+}
+
+macro_rules! primitive {
+  ( $( $method:ident => $kind:literal, $visit:ident[ $( $v:expr ),* ]; )* ) => {
+    $(
+      fn $method< V: Visitor< 'de > >( self, visitor: V ) -> Result< V::Value, Error > {
+        self.write( $kind );
+        let candidates = [ $( $v ),* ];
+        visitor.$visit( candidates[ self.leaf( candidates.len( ) ) ] )
+      }
+    )*
+  }
+}
+
+impl< 'de > Deserializer< 'de > for &mut Tracer {
+  type Error = Error;
+
+  primitive! {
+    deserialize_bool       => "bool", visit_bool[ false, true ];
+    deserialize_i8         => "i8", visit_i8[ 0, 1 ];
+    deserialize_i16        => "i16", visit_i16[ 0, 1 ];
+    deserialize_i32        => "i32", visit_i32[ 0, 1 ];
+    deserialize_i64        => "i64", visit_i64[ 0, 1 ];
+    deserialize_i128       => "i128", visit_i128[ 0, 1 ];
+    deserialize_u8         => "u8", visit_u8[ 0, 1 ];
+    deserialize_u16        => "u16", visit_u16[ 0, 1 ];
+    deserialize_u32        => "u32", visit_u32[ 0, 1 ];
+    deserialize_u64        => "u64", visit_u64[ 0, 1 ];
+    deserialize_u128       => "u128", visit_u128[ 0, 1 ];
+    deserialize_f32        => "f32", visit_f32[ 0.0, 1.0 ];
+    deserialize_f64        => "f64", visit_f64[ 0.0, 1.0 ];
+    deserialize_char       => "char", visit_char[ '\0', 'a' ];
+    // Strings and byte arrays are encoded alike, whether owned or not
+    deserialize_str        => "str", visit_borrowed_str[ "", "0", "a" ];
+    deserialize_string     => "str", visit_borrowed_str[ "", "0", "a" ];
+    deserialize_bytes      => "bytes", visit_borrowed_bytes[ &[ ][ .. ], &[ 0 ][ .. ] ];
+    deserialize_byte_buf   => "bytes", visit_borrowed_bytes[ &[ ][ .. ], &[ 0 ][ .. ] ];
+    deserialize_identifier => "variant", visit_u64[ 0 ];
+  }
+
+  fn deserialize_unit< V: Visitor< 'de > >( self, visitor: V ) -> Result< V::Value, Error > {
+    self.write( "unit" );
+    self.leaf( 1 );
+    visitor.visit_unit( )
+  }
+
+  // The shape of such types is unknown, so they are only known to be present.
+  // Placeholders of several kinds are tried, until one is accepted.
+  fn deserialize_any< V: Visitor< 'de > >( self, visitor: V ) -> Result< V::Value, Error > {
+    self.write( "any" );
+    match self.leaf( 5 ) {
+      0 => self.visit_elements( 0, None, visitor ),
+      1 => visitor.visit_map( Entries { tracer: self, len: 0 } ),
+      2 => visitor.visit_borrowed_str( "" ),
+      3 => visitor.visit_u64( 0 ),
+      _ => visitor.visit_unit( )
+    }
+  }
+
+  fn deserialize_ignored_any< V: Visitor< 'de > >( self, visitor: V ) -> Result< V::Value, Error > {
+    self.deserialize_any( visitor )
+  }
+
+  fn deserialize_option< V: Visitor< 'de > >( self, visitor: V ) -> Result< V::Value, Error > {
+    if !self.recording( ) {
+      return visitor.visit_none( );
+    }
+    self.write( "option<" );
+    // `None` is the fallback for types that reject every placeholder
+    let res = match self.leaf( 2 ) {
+      0 => visitor.visit_some( &mut *self )?,
+      _ => visitor.visit_none( )?
+    };
+    self.write( ">" );
+    Ok( res )
+  }
+
+  fn deserialize_unit_struct< V: Visitor< 'de > >( self, _name: &'static str, visitor: V ) -> Result< V::Value, Error > {
+    self.deserialize_unit( visitor )
+  }
+
+  fn deserialize_newtype_struct< V: Visitor< 'de > >( self, name: &'static str, visitor: V ) -> Result< V::Value, Error > {
+    let key = Key { kind: "newtype", name, names: &[ ], len: 1 };
+    self.trace_struct( key, |tracer| visitor.visit_newtype_struct( &mut *tracer ) )
+  }
+
+  fn deserialize_seq< V: Visitor< 'de > >( self, visitor: V ) -> Result< V::Value, Error > {
+    if !self.recording( ) {
+      return self.visit_elements( 0, None, visitor );
+    }
+    self.write( "seq<" );
+    let res = self.visit_elements( 1, None, visitor )?;
+    self.write( ">" );
+    Ok( res )
+  }
+
+  fn deserialize_tuple< V: Visitor< 'de > >( self, len: usize, visitor: V ) -> Result< V::Value, Error > {
+    self.write( "tuple(" );
+    let res = self.visit_elements( len, None, visitor )?;
+    self.write( ")" );
+    Ok( res )
+  }
+
+  fn deserialize_tuple_struct< V: Visitor< 'de > >( self, name: &'static str, len: usize, visitor: V ) -> Result< V::Value, Error > {
+    let key = Key { kind: "tuple", name, names: &[ ], len };
+    self.trace_struct( key, |tracer| tracer.deserialize_tuple( len, visitor ) )
+  }
+
+  fn deserialize_map< V: Visitor< 'de > >( self, visitor: V ) -> Result< V::Value, Error > {
+    let len = self.recording( ) as usize;
+    self.write( "map<" );
+    let res = visitor.visit_map( Entries { tracer: &mut *self, len } )?;
+    self.write( ">" );
+    Ok( res )
+  }
+
+  fn deserialize_struct< V: Visitor< 'de > >( self, name: &'static str, fields: &'static [&'static str], visitor: V ) -> Result< V::Value, Error > {
+    let key = Key { kind: "struct", name, names: fields, len: fields.len( ) };
+    self.trace_struct( key, |tracer| {
+      tracer.write( "struct{" );
+      let res = tracer.visit_elements( fields.len( ), Some( fields ), visitor )?;
+      tracer.write( "}" );
+      Ok( res )
+    } )
+  }
+
+  fn deserialize_enum< V: Visitor< 'de > >( self, name: &'static str, variants: &'static [&'static str], visitor: V ) -> Result< V::Value, Error > {
+    if variants.is_empty( ) {
+      return Err( de::Error::custom( "cannot trace an enum without variants" ) );
+    }
+    let key = Key { kind: "enum", name, names: variants, len: variants.len( ) };
+    let index = self.enter( key, || Def::Enum( vec![ None; variants.len( ) ] ) )?;
+    let variant = match index {
+      Some( index ) if self.recording( ) => self.choose_variant( index ),
+      // Cycle through the variants by nesting, such that recursion ends
+      _ => ( self.stack.iter( ).filter( |k| **k == key ).count( ) - 1 ) % variants.len( )
+    };
+    let res = visitor.visit_enum( Variant { tracer: &mut *self, variant } );
+    let shape = self.leave( );
+    let val = res?;
+    if let ( Some( index ), Some( shape ) ) = ( index, shape ) {
+      if let Def::Enum( variants ) = &mut self.types[ index ].1 {
+        variants[ variant ].get_or_insert( shape );
+      }
+    }
+    Ok( val )
+  }
+
+  fn is_human_readable( &self ) -> bool {
+    self.human_readable
+  }
+}
+
+/// Placeholder elements of a sequence, tuple or struct
+struct Elements< 'a > {
+  tracer: &'a mut Tracer,
+  len: usize,
+  fields: Option< &'static [&'static str] >,
+  index: usize
+}
+
+impl< 'de > SeqAccess< 'de > for Elements< '_ > {
+  type Error = Error;
+
+  fn next_element_seed< T: DeserializeSeed< 'de > >( &mut self, seed: T ) -> Result< Option< T::Value >, Error > {
+    if self.index == self.len {
+      return Ok( None );
+    }
+    if self.index > 0 {
+      self.tracer.write( "," );
+    }
+    if let Some( field ) = self.fields.and_then( |fields| fields.get( self.index ) ) {
+      self.tracer.write( field );
+      self.tracer.write( ":" );
+    }
+    self.index += 1;
+    seed.deserialize( &mut *self.tracer ).map( Some )
+  }
+
+  fn size_hint( &self ) -> Option< usize > {
+    Some( self.len - self.index )
+  }
+}
+
+/// Placeholder entries of a map
+struct Entries< 'a > {
+  tracer: &'a mut Tracer,
+  len: usize
+}
+
+impl< 'de > MapAccess< 'de > for Entries< '_ > {
+  type Error = Error;
+
+  fn next_key_seed< K: DeserializeSeed< 'de > >( &mut self, seed: K ) -> Result< Option< K::Value >, Error > {
+    if self.len == 0 {
+      return Ok( None );
+    }
+    seed.deserialize( &mut *self.tracer ).map( Some )
+  }
+
+  fn next_value_seed< V: DeserializeSeed< 'de > >( &mut self, seed: V ) -> Result< V::Value, Error > {
+    self.len -= 1;
+    self.tracer.write( "," );
+    seed.deserialize( &mut *self.tracer )
+  }
+
+  fn size_hint( &self ) -> Option< usize > {
+    Some( self.len )
+  }
+}
+
+/// The variant of an enum being traced
+struct Variant< 'a > {
+  tracer: &'a mut Tracer,
+  variant: usize
+}
+
+impl< 'de > EnumAccess< 'de > for Variant< '_ > {
+  type Error = Error;
+
+  type Variant = Self;
+
+  fn variant_seed< V: DeserializeSeed< 'de > >( self, seed: V ) -> Result< ( V::Value, Self ), Error > {
+    let variant: de::value::U32Deserializer< Error > = ( self.variant as u32 ).into_deserializer( );
+    let val = seed.deserialize( variant )?;
+    Ok( ( val, self ) )
+  }
+}
+
+impl< 'de > VariantAccess< 'de > for Variant< '_ > {
+  type Error = Error;
+
+  fn unit_variant( self ) -> Result< (), Error > {
+    self.tracer.write( "unit" );
+    Ok( () )
+  }
+
+  fn newtype_variant_seed< T: DeserializeSeed< 'de > >( self, seed: T ) -> Result< T::Value, Error > {
+    seed.deserialize( &mut *self.tracer )
+  }
+
+  fn tuple_variant< V: Visitor< 'de > >( self, len: usize, visitor: V ) -> Result< V::Value, Error > {
+    self.tracer.deserialize_tuple( len, visitor )
+  }
+
+  fn struct_variant< V: Visitor< 'de > >( self, fields: &'static [&'static str], visitor: V ) -> Result< V::Value, Error > {
+    self.tracer.write( "struct{" );
+    let res = self.tracer.visit_elements( fields.len( ), Some( fields ), visitor )?;
+    self.tracer.write( "}" );
+    Ok( res )
+  }
+}
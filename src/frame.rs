@@ -0,0 +1,194 @@
+//! Length-delimited framing of values over byte streams
+//!
+//! Each frame is a little-endian `u32` length, followed by that many bytes of
+//! the value, as serialized by `serialize_with`. The prefix has a fixed byte
+//! order, so frames can be exchanged between machines.
+
+// stdlib imports
+use std::io::{self, Read};
+use std::mem;
+// external library imports
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+// local imports
+use crate::config::Config;
+use crate::deserialize::BinDeserializer;
+use crate::serialize::BinSerializer;
+use crate::{checksum, de, ser};
+
+
+const PREFIX_LEN: usize = 4;
+
+/// Default of `max_frame_len` (16 MiB)
+const DEFAULT_MAX_FRAME_LEN: usize = 1 << 24;
+
+/// Writes values as length-prefixed frames
+///
+/// Each frame is written with a single `write_all`, so the writer should be
+/// buffered only when many small frames are written at once.
+pub struct FrameWriter< W: io::Write > {
+  inner: W,
+  config: Config,
+  max_frame_len: usize,
+  /// The frame being written, reused between frames
+  buf: Vec< u8 >
+}
+
+impl< W: io::Write > FrameWriter< W > {
+  #[inline]
+  pub fn new( inner: W ) -> Self {
+    FrameWriter::with_config( inner, Config::default( ) )
+  }
+
+  #[inline]
+  pub fn with_config( inner: W, config: Config ) -> Self {
+    FrameWriter { inner, config, max_frame_len: DEFAULT_MAX_FRAME_LEN, buf: Vec::new( ) }
+  }
+
+  /// Maximum length of a frame, excluding its length prefix. Larger values
+  /// fail with `FrameTooLarge`, without writing anything. At most `u32::MAX`.
+  #[inline]
+  pub fn max_frame_len( mut self, v: usize ) -> Self {
+    self.max_frame_len = v;
+    self
+  }
+
+  /// Writes `val` as a single frame
+  pub fn write< T: Serialize >( &mut self, val: &T ) -> Result< (), ser::Error > {
+    let config = self.config;
+    let mut buf = mem::take( &mut self.buf );
+    buf.clear( );
+    buf.extend_from_slice( &[0u8; PREFIX_LEN] );
+    let mut s = BinSerializer::with_output( buf, config );
+    let res = if config.envelope { s.write_envelope( ) } else { Ok( () ) };
+    let res = res.and_then( |()| val.serialize( &mut s ).map_err( |err| crate::locate( val, config, err ) ) );
+    self.buf = s.into_output( );
+    res?;
+    if config.checksum {
+      let trailer = checksum::trailer( &self.buf[ PREFIX_LEN.. ] );
+      self.buf.extend_from_slice( &trailer );
+    }
+    let len = self.buf.len( ) - PREFIX_LEN;
+    if len > self.max_frame_len {
+      return Err( ser::Error::FrameTooLarge );
+    }
+    let len = u32::try_from( len ).map_err( |_| ser::Error::FrameTooLarge )?;
+    self.buf[ ..PREFIX_LEN ].copy_from_slice( &len.to_le_bytes( ) );
+    self.inner.write_all( &self.buf ).map_err( ser::Error::Io )
+  }
+
+  #[inline]
+  pub fn flush( &mut self ) -> Result< (), ser::Error > {
+    self.inner.flush( ).map_err( ser::Error::Io )
+  }
+
+  #[inline]
+  pub fn get_ref( &self ) -> &W {
+    &self.inner
+  }
+
+  #[inline]
+  pub fn get_mut( &mut self ) -> &mut W {
+    &mut self.inner
+  }
+
+  #[inline]
+  pub fn into_inner( self ) -> W {
+    self.inner
+  }
+}
+
+/// Reads values from length-prefixed frames, as written by `FrameWriter`
+///
+/// Short reads are retried until a frame is complete. The stream may end
+/// between frames, but not within one. After an error, the position in the
+/// stream is unknown, so no further frames should be read.
+pub struct FrameReader< R: io::Read > {
+  inner: R,
+  config: Config,
+  max_frame_len: usize,
+  /// The frame being read, reused between frames
+  buf: Vec< u8 >
+}
+
+impl< R: io::Read > FrameReader< R > {
+  #[inline]
+  pub fn new( inner: R ) -> Self {
+    FrameReader::with_config( inner, Config::default( ) )
+  }
+
+  #[inline]
+  pub fn with_config( inner: R, config: Config ) -> Self {
+    FrameReader { inner, config, max_frame_len: DEFAULT_MAX_FRAME_LEN, buf: Vec::new( ) }
+  }
+
+  /// Maximum length of a frame, excluding its length prefix. Longer frames
+  /// fail with `FrameTooLarge`, before any of their bytes are read.
+  #[inline]
+  pub fn max_frame_len( mut self, v: usize ) -> Self {
+    self.max_frame_len = v;
+    self
+  }
+
+  /// Reads the value of the next frame. Returns `None` if the stream ended
+  /// cleanly before it, and fails with `UnexpectedEof` if it ended within it.
+  /// Fails with `TrailingBytes` if the value does not fill the frame.
+  pub fn read< T: DeserializeOwned >( &mut self ) -> Result< Option< T >, de::Error > {
+    let Some( len ) = self.read_len( )? else {
+      return Ok( None );
+    };
+    self.buf.clear( );
+    // The buffer only grows as data arrives, so a bogus length cannot trigger
+    // a huge allocation up front
+    ( &mut self.inner ).take( len as u64 ).read_to_end( &mut self.buf ).map_err( de::Error::Io )?;
+    if self.buf.len( ) < len {
+      return Err( de::Error::UnexpectedEof );
+    }
+    let config = self.config;
+    let xs = checksum::strip( &self.buf, config )?;
+    let mut d = BinDeserializer::new( xs, config );
+    if config.envelope {
+      d.read_envelope( ).map_err( |err| d.locate( err, None ) )?;
+    }
+    let res = T::deserialize( &mut d ).map_err( |err| d.locate( err, None ) )?;
+    // The frame length is authoritative, so the value must fill the frame,
+    // even without strict mode
+    d.end( ).map_err( |err| d.locate( err, None ) )?;
+    Ok( Some( res ) )
+  }
+
+  /// Reads a length prefix, unless the stream ends before it
+  fn read_len( &mut self ) -> Result< Option< usize >, de::Error > {
+    let mut prefix = [0u8; PREFIX_LEN];
+    let mut n = 0;
+    while n < PREFIX_LEN {
+      match self.inner.read( &mut prefix[ n.. ] ) {
+        Ok( 0 ) if n == 0 => return Ok( None ),
+        Ok( 0 ) => return Err( de::Error::UnexpectedEof ),
+        Ok( k ) => n += k,
+        Err( err ) if err.kind( ) == io::ErrorKind::Interrupted => { },
+        Err( err ) => return Err( de::Error::Io( err ) )
+      }
+    }
+    let len = u32::from_le_bytes( prefix ) as usize;
+    if len > self.max_frame_len {
+      return Err( de::Error::FrameTooLarge );
+    }
+    Ok( Some( len ) )
+  }
+
+  #[inline]
+  pub fn get_ref( &self ) -> &R {
+    &self.inner
+  }
+
+  #[inline]
+  pub fn get_mut( &mut self ) -> &mut R {
+    &mut self.inner
+  }
+
+  #[inline]
+  pub fn into_inner( self ) -> R {
+    self.inner
+  }
+}
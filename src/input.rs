@@ -0,0 +1,260 @@
+// stdlib imports
+#[cfg(feature = "alloc")]
+use alloc::borrow::{Cow, ToOwned};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+// local imports
+use crate::deserialize::Error;
+
+
+/// Bytes read from an `Input`. Borrowed from the input itself, if possible.
+pub enum Bytes< 'de, 'a > {
+  Borrowed( &'de [u8] ),
+  Copied( &'a [u8] )
+}
+
+impl Bytes< '_, '_ > {
+  #[inline]
+  pub fn as_slice( &self ) -> &[u8] {
+    match self {
+      Bytes::Borrowed( xs ) => xs,
+      Bytes::Copied( xs ) => xs
+    }
+  }
+}
+
+/// Source of a `BinDeserializer`
+///
+/// Besides reading bytes, an input supports *captures*, which return all bytes
+/// read since the capture began. Captures nest. They require `alloc`.
+pub trait Input< 'de > {
+  fn read_u8( &mut self ) -> Result< u8, Error >;
+
+  fn read_array< const N: usize >( &mut self ) -> Result< [u8; N], Error >;
+
+  fn read_bytes( &mut self, n: usize ) -> Result< Bytes< 'de, '_ >, Error >;
+
+  /// Reads `n` bytes into an owned buffer
+  #[cfg(feature = "alloc")]
+  #[inline]
+  fn read_byte_buf( &mut self, n: usize ) -> Result< Vec< u8 >, Error > {
+    Ok( self.read_bytes( n )?.as_slice( ).to_owned( ) )
+  }
+
+  /// Starts a capture. Returns a token for `end_capture`.
+  #[cfg(feature = "alloc")]
+  fn begin_capture( &mut self ) -> usize;
+
+  /// Ends the innermost capture, and returns the bytes read since it began
+  #[cfg(feature = "alloc")]
+  fn end_capture( &mut self, start: usize ) -> Cow< 'de, [u8] >;
+
+  /// Checks that the input is exhausted. May consume input otherwise.
+  fn end( &mut self ) -> Result< (), Error >;
+}
+
+/// Input from a byte slice, from which strings and byte arrays are borrowed
+#[derive(Default)]
+pub struct SliceInput< 'de > {
+  input: &'de [u8],
+  pos: usize
+}
+
+impl< 'de > SliceInput< 'de > {
+  #[inline]
+  pub fn new( input: &'de [u8] ) -> Self {
+    SliceInput { input, pos: 0 }
+  }
+
+  /// The unconsumed part of the input
+  #[inline]
+  pub fn remaining( &self ) -> &'de [u8] {
+    &self.input[ self.pos.. ]
+  }
+
+  #[inline]
+  fn read_slice( &mut self, n: usize ) -> Result< &'de [u8], Error > {
+    if self.input.len( ) - self.pos >= n {
+      let input: &'de [u8] = self.input;
+      let res = &input[ self.pos..self.pos + n ];
+      self.pos += n;
+      Ok( res )
+    } else {
+      Err( Error::InsufficientData )
+    }
+  }
+}
+
+impl< 'de > Input< 'de > for SliceInput< 'de > {
+  #[inline]
+  fn read_u8( &mut self ) -> Result< u8, Error > {
+    if let Some( &res ) = self.input.get( self.pos ) {
+      self.pos += 1;
+      Ok( res )
+    } else {
+      Err( Error::InsufficientData )
+    }
+  }
+
+  #[inline]
+  fn read_array< const N: usize >( &mut self ) -> Result< [u8; N], Error > {
+    let res = self.read_slice( N )?;
+    Ok( unsafe { res.try_into( ).unwrap_unchecked( ) } )
+  }
+
+  #[inline]
+  fn read_bytes( &mut self, n: usize ) -> Result< Bytes< 'de, '_ >, Error > {
+    Ok( Bytes::Borrowed( self.read_slice( n )? ) )
+  }
+
+  #[cfg(feature = "alloc")]
+  #[inline]
+  fn begin_capture( &mut self ) -> usize {
+    self.pos
+  }
+
+  #[cfg(feature = "alloc")]
+  #[inline]
+  fn end_capture( &mut self, start: usize ) -> Cow< 'de, [u8] > {
+    let input: &'de [u8] = self.input;
+    Cow::Borrowed( &input[ start..self.pos ] )
+  }
+
+  #[inline]
+  fn end( &mut self ) -> Result< (), Error > {
+    if self.pos == self.input.len( ) {
+      Ok( () )
+    } else {
+      Err( Error::TrailingBytes )
+    }
+  }
+}
+
+/// Input from any `std::io::Read`
+///
+/// Exactly the bytes of each value are read, so consecutive values can be
+/// read from the same reader. Consider wrapping unbuffered readers (e.g.,
+/// files) in a `std::io::BufReader`. Strings and byte arrays are copied.
+#[cfg(feature = "std")]
+pub struct IoReader< R: io::Read > {
+  inner: R,
+  scratch: Vec< u8 >,
+  /// Bytes read since the outermost capture began
+  captured: Vec< u8 >,
+  /// Number of currently open captures
+  capture_depth: usize
+}
+
+#[cfg(feature = "std")]
+impl< R: io::Read > IoReader< R > {
+  #[inline]
+  pub fn new( inner: R ) -> Self {
+    IoReader { inner, scratch: Vec::new( ), captured: Vec::new( ), capture_depth: 0 }
+  }
+
+  #[inline]
+  pub fn into_inner( self ) -> R {
+    self.inner
+  }
+
+  #[inline]
+  fn read_exact( &mut self, xs: &mut [u8] ) -> Result< (), Error > {
+    self.inner.read_exact( xs ).map_err( map_io_error )?;
+    if self.capture_depth > 0 {
+      self.captured.extend_from_slice( xs );
+    }
+    Ok( () )
+  }
+
+  /// Reads `n` bytes into the end of `buf`. Unlike `read_exact`, the buffer
+  /// only grows as data actually arrives, so a bogus length prefix cannot
+  /// trigger a huge allocation up front.
+  #[inline]
+  fn read_into( inner: &mut R, n: usize, buf: &mut Vec< u8 > ) -> Result< (), Error > {
+    let start = buf.len( );
+    inner.by_ref( ).take( n as u64 ).read_to_end( buf ).map_err( map_io_error )?;
+    if buf.len( ) - start == n {
+      Ok( () )
+    } else {
+      Err( Error::UnexpectedEof )
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl< 'de, R: io::Read > Input< 'de > for IoReader< R > {
+  #[inline]
+  fn read_u8( &mut self ) -> Result< u8, Error > {
+    let mut res = [0u8; 1];
+    self.read_exact( &mut res )?;
+    Ok( res[ 0 ] )
+  }
+
+  #[inline]
+  fn read_array< const N: usize >( &mut self ) -> Result< [u8; N], Error > {
+    let mut res = [0u8; N];
+    self.read_exact( &mut res )?;
+    Ok( res )
+  }
+
+  #[inline]
+  fn read_bytes( &mut self, n: usize ) -> Result< Bytes< 'de, '_ >, Error > {
+    self.scratch.clear( );
+    Self::read_into( &mut self.inner, n, &mut self.scratch )?;
+    if self.capture_depth > 0 {
+      self.captured.extend_from_slice( &self.scratch );
+    }
+    Ok( Bytes::Copied( &self.scratch ) )
+  }
+
+  #[inline]
+  fn read_byte_buf( &mut self, n: usize ) -> Result< Vec< u8 >, Error > {
+    let mut res = Vec::new( );
+    Self::read_into( &mut self.inner, n, &mut res )?;
+    if self.capture_depth > 0 {
+      self.captured.extend_from_slice( &res );
+    }
+    Ok( res )
+  }
+
+  #[inline]
+  fn begin_capture( &mut self ) -> usize {
+    self.capture_depth += 1;
+    self.captured.len( )
+  }
+
+  #[inline]
+  fn end_capture( &mut self, start: usize ) -> Cow< 'de, [u8] > {
+    self.capture_depth -= 1;
+    let res = self.captured[ start.. ].to_owned( );
+    if self.capture_depth == 0 {
+      self.captured.clear( );
+    }
+    Cow::Owned( res )
+  }
+
+  #[inline]
+  fn end( &mut self ) -> Result< (), Error > {
+    let mut buf = [0u8; 1];
+    loop {
+      match self.inner.read( &mut buf ) {
+        Ok( 0 ) => return Ok( () ),
+        Ok( _ ) => return Err( Error::TrailingBytes ),
+        Err( err ) if err.kind( ) == io::ErrorKind::Interrupted => { },
+        Err( err ) => return Err( Error::Io( err ) )
+      }
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+#[inline]
+fn map_io_error( err: io::Error ) -> Error {
+  if err.kind( ) == io::ErrorKind::UnexpectedEof {
+    Error::UnexpectedEof
+  } else {
+    Error::Io( err )
+  }
+}
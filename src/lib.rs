@@ -1,25 +1,282 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+// The tests use `std`, even where the crate does not
+#[cfg(all(test, not(feature = "std")))]
+extern crate std;
+
+mod checksum;
+mod config;
+mod envelope;
+#[cfg(feature = "alloc")]
+mod fingerprint;
+#[cfg(feature = "std")]
+mod frame;
+mod input;
+mod output;
+mod options;
+mod path;
+#[cfg(feature = "alloc")]
+mod locate;
 mod serialize;
+mod tag;
 mod deserialize;
 mod test;
 
 pub mod ser {
-  pub use super::serialize::Error;
+  pub use super::serialize::{Compound, Error};
+  #[cfg(feature = "alloc")]
+  pub use super::serialize::Context;
+  pub use super::output::{Output, SizeCounter, SliceWriter};
+  #[cfg(feature = "std")]
+  pub use super::output::IoWriter;
 }
 
 pub mod de {
   pub use super::deserialize::Error;
+  #[cfg(feature = "alloc")]
+  pub use super::deserialize::Context;
+  pub use super::input::{Bytes, Input, SliceInput};
+  #[cfg(feature = "std")]
+  pub use super::input::IoReader;
 }
 
+pub use config::{ByteOrder, CharEncoding, Config, IntEncoding, IntWidth, Limit, Limits};
+pub use options::Options;
+#[cfg(feature = "std")]
+pub use frame::{FrameReader, FrameWriter};
+pub use path::Segment;
+#[cfg(feature = "alloc")]
+pub use path::Path;
 pub use serialize::BinSerializer;
 pub use deserialize::BinDeserializer;
 
-pub fn serialize< T: serde::Serialize >( val: &T ) -> Result< Vec< u8 >, ser::Error > {
-  let mut s = BinSerializer::default( );
-  val.serialize( &mut s )?;
-  Ok( s.into( ) )
+#[cfg(feature = "alloc")]
+pub fn serialize< T: serde::Serialize >( val: &T ) -> Result< alloc::vec::Vec< u8 >, ser::Error > {
+  serialize_with( val, Config::default( ) )
+}
+
+/// The buffer grows as the value is written, and is not sized up front with
+/// `serialized_size`.
+#[cfg(feature = "alloc")]
+pub fn serialize_with< T: serde::Serialize >( val: &T, config: Config ) -> Result< alloc::vec::Vec< u8 >, ser::Error > {
+  let mut s = BinSerializer::new( config );
+  if config.envelope {
+    s.write_envelope( )?;
+  }
+  val.serialize( &mut s ).map_err( |err| locate( val, config, err ) )?;
+  let mut res: alloc::vec::Vec< u8 > = s.into( );
+  if config.checksum {
+    res.extend_from_slice( &checksum::trailer( &res ) );
+  }
+  Ok( res )
+}
+
+/// Computes the number of bytes `serialize` would produce for `val`, without
+/// producing them. Fails with the same errors.
+pub fn serialized_size< T: serde::Serialize >( val: &T ) -> Result< usize, ser::Error > {
+  serialized_size_with( val, Config::default( ) )
+}
+
+pub fn serialized_size_with< T: serde::Serialize >( val: &T, config: Config ) -> Result< usize, ser::Error > {
+  let mut s = BinSerializer::with_output( ser::SizeCounter::new( ), config );
+  if config.envelope {
+    s.write_envelope( )?;
+  }
+  val.serialize( &mut s ).map_err( |err| locate( val, config, err ) )?;
+  let trailer = if config.checksum { checksum::LEN } else { 0 };
+  Ok( s.into_output( ).size( ) + trailer )
+}
+
+/// Serializes `val` directly into `writer`, without buffering the whole value.
+/// Only sequences and maps of unknown length are buffered, as their length
+/// prefix precedes them.
+#[cfg(feature = "std")]
+pub fn serialize_into_writer< W: std::io::Write, T: serde::Serialize >( writer: W, val: &T ) -> Result< (), ser::Error > {
+  serialize_into_writer_with( writer, val, Config::default( ) )
+}
+
+#[cfg(feature = "std")]
+pub fn serialize_into_writer_with< W: std::io::Write, T: serde::Serialize >( writer: W, val: &T, config: Config ) -> Result< (), ser::Error > {
+  if config.checksum {
+    let mut writer = checksum::CrcWriter::new( writer );
+    write_into_writer( &mut writer, val, config )?;
+    return writer.finish( ).map_err( ser::Error::Io );
+  }
+  write_into_writer( writer, val, config )
+}
+
+#[cfg(feature = "std")]
+fn write_into_writer< W: std::io::Write, T: serde::Serialize >( writer: W, val: &T, config: Config ) -> Result< (), ser::Error > {
+  let mut s = BinSerializer::with_output( ser::IoWriter::new( writer ), config );
+  if config.envelope {
+    s.write_envelope( )?;
+  }
+  val.serialize( &mut s ).map_err( |err| locate( val, config, err ) )
+}
+
+/// Serializes `val` into `buf`, without allocating. Returns the number of
+/// bytes written.
+pub fn serialize_to_slice< T: serde::Serialize >( val: &T, buf: &mut [u8] ) -> Result< usize, ser::Error > {
+  serialize_to_slice_with( val, buf, Config::default( ) )
+}
+
+pub fn serialize_to_slice_with< T: serde::Serialize >( val: &T, buf: &mut [u8], config: Config ) -> Result< usize, ser::Error > {
+  let mut s = BinSerializer::with_output( ser::SliceWriter::new( buf ), config );
+  if config.envelope {
+    s.write_envelope( )?;
+  }
+  val.serialize( &mut s ).map_err( |err| locate( val, config, err ) )?;
+  let n = s.into_output( ).written( );
+  if !config.checksum {
+    return Ok( n );
+  }
+  let trailer = checksum::trailer( &buf[ ..n ] );
+  buf.get_mut( n..n + checksum::LEN ).ok_or( ser::Error::BufferTooSmall )?.copy_from_slice( &trailer );
+  Ok( n + checksum::LEN )
+}
+
+/// With `Config::error_context`, serializes `val` again to find where `err`
+/// occurred.
+#[inline]
+fn locate< T: serde::Serialize + ?Sized >( val: &T, config: Config, err: ser::Error ) -> ser::Error {
+  #[cfg(feature = "alloc")]
+  if config.error_context {
+    return locate::locate( val, config, err );
+  }
+  let _ = ( val, config );
+  err
+}
+
+/// Computes the fingerprint of `T`, a hash of the primitive kinds, field
+/// names and variant names of its serde shape. It is stable across platforms
+/// and versions of this crate. The shape is traced by deserializing `T` from
+/// placeholder values (zero, one, empty strings, and so on). Types that reject
+/// all of them fail with `UntraceableType`. The contents of types deserialized
+/// with `deserialize_any`, such as untagged enums, are not traced, so such
+/// types cannot be told apart. Internally tagged enums and structs with
+/// `#[serde(flatten)]` fields fail with `UntraceableType`, as they require map
+/// keys named after their tags or fields.
+#[cfg(feature = "alloc")]
+pub fn fingerprint< 'de, T: serde::Deserialize< 'de > >( ) -> Result< u64, de::Error > {
+  fingerprint_with::< T >( Config::default( ) )
+}
+
+#[cfg(feature = "alloc")]
+pub fn fingerprint_with< 'de, T: serde::Deserialize< 'de > >( config: Config ) -> Result< u64, de::Error > {
+  fingerprint::fingerprint::< T >( config )
+}
+
+/// Serializes `val`, prefixed with the fingerprint of `T`, such that
+/// `deserialize_typed` rejects it as another type. With `std`, the fingerprint
+/// is traced once per type. Otherwise, or for types that borrow, see
+/// `BinSerializer::write_fingerprint`.
+#[cfg(feature = "alloc")]
+pub fn serialize_typed< T: serde::Serialize + for< 'de > serde::Deserialize< 'de > + 'static >( val: &T ) -> Result< alloc::vec::Vec< u8 >, ser::Error > {
+  serialize_typed_with( val, Config::default( ) )
+}
+
+#[cfg(feature = "alloc")]
+pub fn serialize_typed_with< T: serde::Serialize + for< 'de > serde::Deserialize< 'de > + 'static >( val: &T, config: Config ) -> Result< alloc::vec::Vec< u8 >, ser::Error > {
+  let mut s = BinSerializer::new( config );
+  if config.envelope {
+    s.write_envelope( )?;
+  }
+  s.write_fingerprint( fingerprint::cached::< T >( config ).map_err( fingerprint::ser_error )? )?;
+  val.serialize( &mut s ).map_err( |err| locate( val, config, err ) )?;
+  let mut res: alloc::vec::Vec< u8 > = s.into( );
+  if config.checksum {
+    res.extend_from_slice( &checksum::trailer( &res ) );
+  }
+  Ok( res )
+}
+
+/// Deserializes a value written by `serialize_typed`. Fails with
+/// `TypeMismatch` before reading the value, unless it was written as `T`.
+#[cfg(feature = "alloc")]
+pub fn deserialize_typed< T: for< 'de > serde::Deserialize< 'de > + 'static >( xs: &[u8] ) -> Result< T, de::Error > {
+  deserialize_typed_with( xs, Config::default( ) )
+}
+
+#[cfg(feature = "alloc")]
+pub fn deserialize_typed_with< T: for< 'de > serde::Deserialize< 'de > + 'static >( xs: &[u8], config: Config ) -> Result< T, de::Error > {
+  let xs = checksum::strip( xs, config )?;
+  let mut d = BinDeserializer::new( xs, config );
+  if config.envelope {
+    d.read_envelope( ).map_err( |err| d.locate( err, None ) )?;
+  }
+  let expected = fingerprint::cached::< T >( d.config( ) )?;
+  d.check_fingerprint( expected ).map_err( |err| d.locate( err, None ) )?;
+  let res = T::deserialize( &mut d ).map_err( |err| d.locate( err, None ) )?;
+  if config.strict {
+    d.end( ).map_err( |err| d.locate( err, None ) )?;
+  }
+  Ok( res )
 }
 
 pub fn deserialize< T: for< 'de > serde::Deserialize< 'de > >( xs: &[u8] ) -> Result< T, de::Error > {
-  let mut d = BinDeserializer::from( xs );
-  T::deserialize( &mut d )
+  deserialize_with( xs, Config::default( ) )
+}
+
+pub fn deserialize_with< T: for< 'de > serde::Deserialize< 'de > >( xs: &[u8], config: Config ) -> Result< T, de::Error > {
+  from_slice_with( xs, config )
+}
+
+/// Deserializes a value that may borrow strings and byte arrays from `xs`
+pub fn from_slice< 'de, T: serde::Deserialize< 'de > >( xs: &'de [u8] ) -> Result< T, de::Error > {
+  from_slice_with( xs, Config::default( ) )
+}
+
+pub fn from_slice_with< 'de, T: serde::Deserialize< 'de > >( xs: &'de [u8], config: Config ) -> Result< T, de::Error > {
+  let xs = checksum::strip( xs, config )?;
+  let mut d = BinDeserializer::new( xs, config );
+  if config.envelope {
+    d.read_envelope( ).map_err( |err| d.locate( err, None ) )?;
+  }
+  let res = T::deserialize( &mut d ).map_err( |err| d.locate( err, None ) )?;
+  if config.strict {
+    d.end( ).map_err( |err| d.locate( err, None ) )?;
+  }
+  Ok( res )
+}
+
+/// Deserializes a value from `reader`, reading exactly the bytes it occupies.
+/// In strict mode, `reader` must end after the value.
+#[cfg(feature = "std")]
+pub fn deserialize_from_reader< R: std::io::Read, T: serde::de::DeserializeOwned >( reader: R ) -> Result< T, de::Error > {
+  deserialize_from_reader_with( reader, Config::default( ) )
+}
+
+#[cfg(feature = "std")]
+pub fn deserialize_from_reader_with< R: std::io::Read, T: serde::de::DeserializeOwned >( reader: R, config: Config ) -> Result< T, de::Error > {
+  if !config.envelope && !config.checksum {
+    return read_from_reader( reader, config );
+  }
+  // The envelope decides whether a trailer follows, so the checksum is
+  // computed from the first byte either way
+  let reader = checksum::CrcReader::new( reader, 0 );
+  let mut d = BinDeserializer::with_input( de::IoReader::new( reader ), config );
+  if config.envelope {
+    d.read_envelope( ).map_err( |err| d.locate( err, None ) )?;
+  }
+  let config = d.config( );
+  let res = T::deserialize( &mut d ).map_err( |err| d.locate( err, None ) )?;
+  if config.checksum {
+    // The trailer follows the value, so the end is checked after it
+    d.into_input( ).into_inner( ).finish( config.strict )?;
+  } else if config.strict {
+    d.end( ).map_err( |err| d.locate( err, None ) )?;
+  }
+  Ok( res )
+}
+
+#[cfg(feature = "std")]
+fn read_from_reader< R: std::io::Read, T: serde::de::DeserializeOwned >( reader: R, config: Config ) -> Result< T, de::Error > {
+  let mut d = BinDeserializer::with_input( de::IoReader::new( reader ), config );
+  let res = T::deserialize( &mut d ).map_err( |err| d.locate( err, None ) )?;
+  if config.strict {
+    d.end( ).map_err( |err| d.locate( err, None ) )?;
+  }
+  Ok( res )
 }
@@ -0,0 +1,260 @@
+//! Locating serialization errors
+//!
+//! Tracking the path while serializing would slow down every serialization,
+//! so instead a failed value is serialized again through `Locator`, which
+//! records the path as the error propagates outward.
+
+// stdlib imports
+use core::cell::RefCell;
+use core::fmt;
+use alloc::boxed::Box;
+// external imports
+use serde::ser::{self, Serialize, Serializer};
+// local imports
+use crate::config::Config;
+use crate::output::SizeCounter;
+use crate::path::{Path, Segment};
+use crate::serialize::{BinSerializer, Context, Error};
+
+
+/// Serializes `val` again to find where `err` occurred, and wraps it in a
+/// `Context`. `err` is returned as is if the failure does not recur, as for
+/// errors of the output itself.
+#[cold]
+pub(crate) fn locate< T: Serialize + ?Sized >( val: &T, config: Config, err: Error ) -> Error {
+  let track = RefCell::new( Path::default( ) );
+  let mut s = BinSerializer::with_output( SizeCounter::new( ), config );
+  match val.serialize( Locator { ser: &mut s, track: &track } ) {
+    Ok( () ) => err,
+    Err( _ ) => Error::Context( Box::new( Context { path: track.into_inner( ), error: err } ) )
+  }
+}
+
+/// Serializer that records the path to a failing value in `track`
+struct Locator< 'a, S > {
+  ser: S,
+  track: &'a RefCell< Path >
+}
+
+/// Value serialized through a `Locator`
+struct Tracked< 'a, T: ?Sized > {
+  val: &'a T,
+  track: &'a RefCell< Path >
+}
+
+impl< T: Serialize + ?Sized > Serialize for Tracked< '_, T > {
+  #[inline]
+  fn serialize< S: Serializer >( &self, ser: S ) -> Result< S::Ok, S::Error > {
+    self.val.serialize( Locator { ser, track: self.track } )
+  }
+}
+
+/// Compound that records the segment of a failing part in `track`
+struct Parts< 'a, C > {
+  inner: C,
+  track: &'a RefCell< Path >,
+  variant: Option< &'static str >,
+  index: usize
+}
+
+impl< 'a, C > Parts< 'a, C > {
+  #[inline]
+  fn new( inner: C, track: &'a RefCell< Path >, variant: Option< &'static str > ) -> Self {
+    Parts { inner, track, variant, index: 0 }
+  }
+
+  #[cold]
+  fn at< E >( &self, err: E, segment: Segment ) -> E {
+    let mut path = self.track.borrow_mut( );
+    path.push_front( segment );
+    if let Some( variant ) = self.variant {
+      path.push_front( Segment::Variant( variant ) );
+    }
+    err
+  }
+}
+
+macro_rules! forward {
+  ( $( $name:ident( $( $arg:ident: $ty:ty ),* ); )* ) => {
+    $(
+      #[inline]
+      fn $name( self, $( $arg: $ty ),* ) -> Result< Self::Ok, Self::Error > {
+        self.ser.$name( $( $arg ),* )
+      }
+    )*
+  }
+}
+
+impl< 'a, S: Serializer > Serializer for Locator< 'a, S > {
+  type Ok = S::Ok;
+  type Error = S::Error;
+  type SerializeSeq = Parts< 'a, S::SerializeSeq >;
+  type SerializeTuple = Parts< 'a, S::SerializeTuple >;
+  type SerializeTupleStruct = Parts< 'a, S::SerializeTupleStruct >;
+  type SerializeTupleVariant = Parts< 'a, S::SerializeTupleVariant >;
+  type SerializeMap = Parts< 'a, S::SerializeMap >;
+  type SerializeStruct = Parts< 'a, S::SerializeStruct >;
+  type SerializeStructVariant = Parts< 'a, S::SerializeStructVariant >;
+
+  forward! {
+    serialize_bool( v: bool );
+    serialize_i8( v: i8 );
+    serialize_i16( v: i16 );
+    serialize_i32( v: i32 );
+    serialize_i64( v: i64 );
+    serialize_i128( v: i128 );
+    serialize_u8( v: u8 );
+    serialize_u16( v: u16 );
+    serialize_u32( v: u32 );
+    serialize_u64( v: u64 );
+    serialize_u128( v: u128 );
+    serialize_f32( v: f32 );
+    serialize_f64( v: f64 );
+    serialize_char( v: char );
+    serialize_str( v: &str );
+    serialize_bytes( v: &[u8] );
+    serialize_none( );
+    serialize_unit( );
+    serialize_unit_struct( name: &'static str );
+    serialize_unit_variant( name: &'static str, index: u32, variant: &'static str );
+  }
+
+  #[inline]
+  fn serialize_some< T: Serialize + ?Sized >( self, v: &T ) -> Result< Self::Ok, Self::Error > {
+    self.ser.serialize_some( &Tracked { val: v, track: self.track } )
+  }
+
+  #[inline]
+  fn serialize_newtype_struct< T: Serialize + ?Sized >( self, name: &'static str, v: &T ) -> Result< Self::Ok, Self::Error > {
+    self.ser.serialize_newtype_struct( name, &Tracked { val: v, track: self.track } )
+  }
+
+  #[inline]
+  fn serialize_newtype_variant< T: Serialize + ?Sized >( self, name: &'static str, index: u32, variant: &'static str, v: &T ) -> Result< Self::Ok, Self::Error > {
+    let track = self.track;
+    self.ser.serialize_newtype_variant( name, index, variant, &Tracked { val: v, track } ).inspect_err( |_| {
+      track.borrow_mut( ).push_front( Segment::Variant( variant ) );
+    } )
+  }
+
+  #[inline]
+  fn serialize_seq( self, len: Option< usize > ) -> Result< Self::SerializeSeq, Self::Error > {
+    Ok( Parts::new( self.ser.serialize_seq( len )?, self.track, None ) )
+  }
+
+  #[inline]
+  fn serialize_tuple( self, len: usize ) -> Result< Self::SerializeTuple, Self::Error > {
+    Ok( Parts::new( self.ser.serialize_tuple( len )?, self.track, None ) )
+  }
+
+  #[inline]
+  fn serialize_tuple_struct( self, name: &'static str, len: usize ) -> Result< Self::SerializeTupleStruct, Self::Error > {
+    Ok( Parts::new( self.ser.serialize_tuple_struct( name, len )?, self.track, None ) )
+  }
+
+  #[inline]
+  fn serialize_tuple_variant( self, name: &'static str, index: u32, variant: &'static str, len: usize ) -> Result< Self::SerializeTupleVariant, Self::Error > {
+    Ok( Parts::new( self.ser.serialize_tuple_variant( name, index, variant, len )?, self.track, Some( variant ) ) )
+  }
+
+  #[inline]
+  fn serialize_map( self, len: Option< usize > ) -> Result< Self::SerializeMap, Self::Error > {
+    Ok( Parts::new( self.ser.serialize_map( len )?, self.track, None ) )
+  }
+
+  #[inline]
+  fn serialize_struct( self, name: &'static str, len: usize ) -> Result< Self::SerializeStruct, Self::Error > {
+    Ok( Parts::new( self.ser.serialize_struct( name, len )?, self.track, None ) )
+  }
+
+  #[inline]
+  fn serialize_struct_variant( self, name: &'static str, index: u32, variant: &'static str, len: usize ) -> Result< Self::SerializeStructVariant, Self::Error > {
+    Ok( Parts::new( self.ser.serialize_struct_variant( name, index, variant, len )?, self.track, Some( variant ) ) )
+  }
+
+  #[inline]
+  fn collect_str< T: fmt::Display + ?Sized >( self, v: &T ) -> Result< Self::Ok, Self::Error > {
+    self.ser.collect_str( v )
+  }
+
+  #[inline]
+  fn is_human_readable( &self ) -> bool {
+    self.ser.is_human_readable( )
+  }
+}
+
+macro_rules! indexed {
+  ( $trait:ident, $method:ident ) => {
+    impl< C: ser::$trait > ser::$trait for Parts< '_, C > {
+      type Ok = C::Ok;
+      type Error = C::Error;
+
+      #[inline]
+      fn $method< T: Serialize + ?Sized >( &mut self, v: &T ) -> Result< (), Self::Error > {
+        let index = self.index;
+        self.index += 1;
+        self.inner.$method( &Tracked { val: v, track: self.track } ).map_err( |err| self.at( err, Segment::Index( index ) ) )
+      }
+
+      #[inline]
+      fn end( self ) -> Result< Self::Ok, Self::Error > {
+        self.inner.end( )
+      }
+    }
+  }
+}
+
+indexed!( SerializeSeq, serialize_element );
+indexed!( SerializeTuple, serialize_element );
+indexed!( SerializeTupleStruct, serialize_field );
+indexed!( SerializeTupleVariant, serialize_field );
+
+impl< C: ser::SerializeMap > ser::SerializeMap for Parts< '_, C > {
+  type Ok = C::Ok;
+  type Error = C::Error;
+
+  #[inline]
+  fn serialize_key< T: Serialize + ?Sized >( &mut self, key: &T ) -> Result< (), Self::Error > {
+    let index = self.index;
+    self.inner.serialize_key( &Tracked { val: key, track: self.track } ).map_err( |err| self.at( err, Segment::Index( index ) ) )
+  }
+
+  #[inline]
+  fn serialize_value< T: Serialize + ?Sized >( &mut self, v: &T ) -> Result< (), Self::Error > {
+    let index = self.index;
+    self.index += 1;
+    self.inner.serialize_value( &Tracked { val: v, track: self.track } ).map_err( |err| self.at( err, Segment::Index( index ) ) )
+  }
+
+  #[inline]
+  fn end( self ) -> Result< Self::Ok, Self::Error > {
+    self.inner.end( )
+  }
+}
+
+macro_rules! fields {
+  ( $trait:ident ) => {
+    impl< C: ser::$trait > ser::$trait for Parts< '_, C > {
+      type Ok = C::Ok;
+      type Error = C::Error;
+
+      #[inline]
+      fn serialize_field< T: Serialize + ?Sized >( &mut self, key: &'static str, v: &T ) -> Result< (), Self::Error > {
+        self.inner.serialize_field( key, &Tracked { val: v, track: self.track } ).map_err( |err| self.at( err, Segment::Field( key ) ) )
+      }
+
+      #[inline]
+      fn skip_field( &mut self, key: &'static str ) -> Result< (), Self::Error > {
+        self.inner.skip_field( key )
+      }
+
+      #[inline]
+      fn end( self ) -> Result< Self::Ok, Self::Error > {
+        self.inner.end( )
+      }
+    }
+  }
+}
+
+fields!( SerializeStruct );
+fields!( SerializeStructVariant );
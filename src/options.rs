@@ -0,0 +1,241 @@
+// stdlib imports
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+// external library imports
+use serde::{Deserialize, Serialize};
+// local imports
+use crate::config::{ByteOrder, CharEncoding, Config, IntEncoding, IntWidth, Limits};
+use crate::{de, ser, BinDeserializer};
+#[cfg(feature = "alloc")]
+use crate::BinSerializer;
+#[cfg(feature = "std")]
+use crate::{FrameReader, FrameWriter};
+
+
+/// Builder of a `Config`, which serializes and deserializes with it
+///
+/// Both directions share the same settings, so they cannot drift apart. For
+/// example, `Options::new( ).varint( ).strict( true ).serialize( &v )`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+  config: Config
+}
+
+impl Options {
+  /// The default configuration, as used by the free functions
+  #[inline]
+  pub fn new( ) -> Self {
+    Options::default( )
+  }
+
+  #[inline]
+  pub fn config( &self ) -> Config {
+    self.config
+  }
+
+  #[inline]
+  pub fn byte_order( mut self, v: ByteOrder ) -> Self {
+    self.config.byte_order = v;
+    self
+  }
+
+  #[inline]
+  pub fn int_encoding( mut self, v: IntEncoding ) -> Self {
+    self.config.int_encoding = v;
+    self
+  }
+
+  #[inline]
+  pub fn str_len( mut self, v: IntWidth ) -> Self {
+    self.config.str_len = v;
+    self
+  }
+
+  #[inline]
+  pub fn bytes_len( mut self, v: IntWidth ) -> Self {
+    self.config.bytes_len = v;
+    self
+  }
+
+  #[inline]
+  pub fn seq_len( mut self, v: IntWidth ) -> Self {
+    self.config.seq_len = v;
+    self
+  }
+
+  #[inline]
+  pub fn map_len( mut self, v: IntWidth ) -> Self {
+    self.config.map_len = v;
+    self
+  }
+
+  #[inline]
+  pub fn variant_width( mut self, v: IntWidth ) -> Self {
+    self.config.variant_width = v;
+    self
+  }
+
+  #[inline]
+  pub fn char_encoding( mut self, v: CharEncoding ) -> Self {
+    self.config.char_encoding = v;
+    self
+  }
+
+  #[inline]
+  pub fn lenient_bool( mut self, v: bool ) -> Self {
+    self.config.lenient_bool = v;
+    self
+  }
+
+  #[inline]
+  pub fn strict( mut self, v: bool ) -> Self {
+    self.config.strict = v;
+    self
+  }
+
+  #[inline]
+  pub fn limits( mut self, v: Limits ) -> Self {
+    self.config.limits = v;
+    self
+  }
+
+  #[inline]
+  pub fn max_depth( mut self, v: usize ) -> Self {
+    self.config.max_depth = v;
+    self
+  }
+
+  #[inline]
+  pub fn error_context( mut self, v: bool ) -> Self {
+    self.config.error_context = v;
+    self
+  }
+
+  #[inline]
+  pub fn self_describing( mut self, v: bool ) -> Self {
+    self.config.self_describing = v;
+    self
+  }
+
+  #[inline]
+  pub fn human_readable( mut self, v: bool ) -> Self {
+    self.config.human_readable = v;
+    self
+  }
+
+  #[inline]
+  pub fn envelope( mut self, v: bool ) -> Self {
+    self.config.envelope = v;
+    self
+  }
+
+  #[inline]
+  pub fn checksum( mut self, v: bool ) -> Self {
+    self.config.checksum = v;
+    self
+  }
+
+  /// Varint-encoded integers, length prefixes and enum discriminants, as by
+  /// `Config::varint`
+  #[inline]
+  pub fn varint( self ) -> Self {
+    self
+      .int_encoding( IntEncoding::Varint )
+      .str_len( IntWidth::Varint )
+      .bytes_len( IntWidth::Varint )
+      .seq_len( IntWidth::Varint )
+      .map_len( IntWidth::Varint )
+      .variant_width( IntWidth::Varint )
+  }
+
+  #[cfg(feature = "alloc")]
+  #[inline]
+  pub fn serializer( &self ) -> BinSerializer {
+    BinSerializer::new( self.config )
+  }
+
+  #[cfg(feature = "std")]
+  #[inline]
+  pub fn frame_writer< W: std::io::Write >( &self, writer: W ) -> FrameWriter< W > {
+    FrameWriter::with_config( writer, self.config )
+  }
+
+  #[cfg(feature = "std")]
+  #[inline]
+  pub fn frame_reader< R: std::io::Read >( &self, reader: R ) -> FrameReader< R > {
+    FrameReader::with_config( reader, self.config )
+  }
+
+  #[inline]
+  pub fn deserializer< 'de >( &self, xs: &'de [u8] ) -> BinDeserializer< 'de > {
+    BinDeserializer::new( xs, self.config )
+  }
+
+  #[cfg(feature = "alloc")]
+  #[inline]
+  pub fn serialize< T: Serialize >( &self, val: &T ) -> Result< Vec< u8 >, ser::Error > {
+    crate::serialize_with( val, self.config )
+  }
+
+  /// Serializes `val`, prefixed with the fingerprint of `T`, as by
+  /// `crate::serialize_typed`
+  #[cfg(feature = "alloc")]
+  #[inline]
+  pub fn serialize_typed< T: Serialize + for< 'de > Deserialize< 'de > + 'static >( &self, val: &T ) -> Result< Vec< u8 >, ser::Error > {
+    crate::serialize_typed_with( val, self.config )
+  }
+
+  #[inline]
+  pub fn serialized_size< T: Serialize >( &self, val: &T ) -> Result< usize, ser::Error > {
+    crate::serialized_size_with( val, self.config )
+  }
+
+  #[inline]
+  pub fn serialize_to_slice< T: Serialize >( &self, val: &T, buf: &mut [u8] ) -> Result< usize, ser::Error > {
+    crate::serialize_to_slice_with( val, buf, self.config )
+  }
+
+  #[cfg(feature = "std")]
+  #[inline]
+  pub fn serialize_into_writer< W: std::io::Write, T: Serialize >( &self, writer: W, val: &T ) -> Result< (), ser::Error > {
+    crate::serialize_into_writer_with( writer, val, self.config )
+  }
+
+  #[inline]
+  pub fn deserialize< T: for< 'de > Deserialize< 'de > >( &self, xs: &[u8] ) -> Result< T, de::Error > {
+    crate::deserialize_with( xs, self.config )
+  }
+
+  /// Deserializes a value written by `serialize_typed`
+  #[cfg(feature = "alloc")]
+  #[inline]
+  pub fn deserialize_typed< T: for< 'de > Deserialize< 'de > + 'static >( &self, xs: &[u8] ) -> Result< T, de::Error > {
+    crate::deserialize_typed_with( xs, self.config )
+  }
+
+  /// Deserializes a value that may borrow strings and byte arrays from `xs`
+  #[inline]
+  pub fn from_slice< 'de, T: Deserialize< 'de > >( &self, xs: &'de [u8] ) -> Result< T, de::Error > {
+    crate::from_slice_with( xs, self.config )
+  }
+
+  #[cfg(feature = "std")]
+  #[inline]
+  pub fn deserialize_from_reader< R: std::io::Read, T: serde::de::DeserializeOwned >( &self, reader: R ) -> Result< T, de::Error > {
+    crate::deserialize_from_reader_with( reader, self.config )
+  }
+}
+
+impl From< Config > for Options {
+  #[inline]
+  fn from( config: Config ) -> Self {
+    Options { config }
+  }
+}
+
+impl From< Options > for Config {
+  #[inline]
+  fn from( options: Options ) -> Self {
+    options.config
+  }
+}
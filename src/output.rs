@@ -0,0 +1,253 @@
+// stdlib imports
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+// local imports
+use crate::serialize::Error;
+
+
+/// Destination of a `BinSerializer`
+///
+/// Besides appending bytes, an output supports *sections*, which are needed
+/// for length prefixes that are only known after their elements are written.
+/// Within a section, bytes written since its start may still be modified.
+/// Sections nest.
+pub trait Output {
+  fn write_bytes( &mut self, xs: &[u8] ) -> Result< (), Error >;
+
+  #[inline]
+  fn write_u8( &mut self, v: u8 ) -> Result< (), Error > {
+    self.write_bytes( &[ v ] )
+  }
+
+  /// Starts a section. Returns the current position, which is valid for
+  /// `patch` and `insert` until the section ends.
+  fn begin_section( &mut self ) -> usize;
+
+  /// Overwrites previously written bytes at `pos`
+  fn patch( &mut self, pos: usize, xs: &[u8] ) -> Result< (), Error >;
+
+  /// Inserts bytes at `pos`, after which subsequent bytes are shifted
+  fn insert( &mut self, pos: usize, xs: &[u8] ) -> Result< (), Error >;
+
+  /// Ends the most recently started section
+  fn end_section( &mut self ) -> Result< (), Error >;
+}
+
+#[cfg(feature = "alloc")]
+impl Output for Vec< u8 > {
+  #[inline]
+  fn write_bytes( &mut self, xs: &[u8] ) -> Result< (), Error > {
+    self.extend_from_slice( xs );
+    Ok( () )
+  }
+
+  #[inline]
+  fn write_u8( &mut self, v: u8 ) -> Result< (), Error > {
+    self.push( v );
+    Ok( () )
+  }
+
+  #[inline]
+  fn begin_section( &mut self ) -> usize {
+    self.len( )
+  }
+
+  #[inline]
+  fn patch( &mut self, pos: usize, xs: &[u8] ) -> Result< (), Error > {
+    self[ pos..pos + xs.len( ) ].copy_from_slice( xs );
+    Ok( () )
+  }
+
+  #[inline]
+  fn insert( &mut self, pos: usize, xs: &[u8] ) -> Result< (), Error > {
+    self.splice( pos..pos, xs.iter( ).copied( ) );
+    Ok( () )
+  }
+
+  #[inline]
+  fn end_section( &mut self ) -> Result< (), Error > {
+    Ok( () )
+  }
+}
+
+/// Output into a fixed, caller-provided buffer, which never allocates
+///
+/// Fails with `BufferTooSmall` when the buffer is full.
+pub struct SliceWriter< 'a > {
+  buf: &'a mut [u8],
+  pos: usize
+}
+
+impl< 'a > SliceWriter< 'a > {
+  #[inline]
+  pub fn new( buf: &'a mut [u8] ) -> Self {
+    SliceWriter { buf, pos: 0 }
+  }
+
+  /// Number of bytes written so far
+  #[inline]
+  pub fn written( &self ) -> usize {
+    self.pos
+  }
+}
+
+impl Output for SliceWriter< '_ > {
+  #[inline]
+  fn write_bytes( &mut self, xs: &[u8] ) -> Result< (), Error > {
+    if self.buf.len( ) - self.pos < xs.len( ) {
+      return Err( Error::BufferTooSmall );
+    }
+    self.buf[ self.pos..self.pos + xs.len( ) ].copy_from_slice( xs );
+    self.pos += xs.len( );
+    Ok( () )
+  }
+
+  #[inline]
+  fn begin_section( &mut self ) -> usize {
+    self.pos
+  }
+
+  #[inline]
+  fn patch( &mut self, pos: usize, xs: &[u8] ) -> Result< (), Error > {
+    self.buf[ pos..pos + xs.len( ) ].copy_from_slice( xs );
+    Ok( () )
+  }
+
+  #[inline]
+  fn insert( &mut self, pos: usize, xs: &[u8] ) -> Result< (), Error > {
+    if self.buf.len( ) - self.pos < xs.len( ) {
+      return Err( Error::BufferTooSmall );
+    }
+    self.buf.copy_within( pos..self.pos, pos + xs.len( ) );
+    self.buf[ pos..pos + xs.len( ) ].copy_from_slice( xs );
+    self.pos += xs.len( );
+    Ok( () )
+  }
+
+  #[inline]
+  fn end_section( &mut self ) -> Result< (), Error > {
+    Ok( () )
+  }
+}
+
+/// Output that only counts the bytes written to it
+#[derive(Default)]
+pub struct SizeCounter {
+  size: usize
+}
+
+impl SizeCounter {
+  #[inline]
+  pub fn new( ) -> Self {
+    SizeCounter { size: 0 }
+  }
+
+  /// Number of bytes written so far
+  #[inline]
+  pub fn size( &self ) -> usize {
+    self.size
+  }
+}
+
+impl Output for SizeCounter {
+  #[inline]
+  fn write_bytes( &mut self, xs: &[u8] ) -> Result< (), Error > {
+    self.size += xs.len( );
+    Ok( () )
+  }
+
+  #[inline]
+  fn write_u8( &mut self, _v: u8 ) -> Result< (), Error > {
+    self.size += 1;
+    Ok( () )
+  }
+
+  #[inline]
+  fn begin_section( &mut self ) -> usize {
+    self.size
+  }
+
+  #[inline]
+  fn patch( &mut self, _pos: usize, _xs: &[u8] ) -> Result< (), Error > {
+    Ok( () )
+  }
+
+  #[inline]
+  fn insert( &mut self, _pos: usize, xs: &[u8] ) -> Result< (), Error > {
+    self.size += xs.len( );
+    Ok( () )
+  }
+
+  #[inline]
+  fn end_section( &mut self ) -> Result< (), Error > {
+    Ok( () )
+  }
+}
+
+/// Output to any `std::io::Write`
+///
+/// Bytes are passed on to the writer directly, except within sections (i.e.,
+/// sequences and maps of unknown length), which are buffered until the
+/// outermost section ends. The writer is not flushed.
+#[cfg(feature = "std")]
+pub struct IoWriter< W: io::Write > {
+  inner: W,
+  /// Bytes of the currently open sections
+  buf: Vec< u8 >,
+  /// Number of currently open sections
+  depth: usize
+}
+
+#[cfg(feature = "std")]
+impl< W: io::Write > IoWriter< W > {
+  #[inline]
+  pub fn new( inner: W ) -> Self {
+    IoWriter { inner, buf: Vec::new( ), depth: 0 }
+  }
+
+  #[inline]
+  pub fn into_inner( self ) -> W {
+    self.inner
+  }
+}
+
+#[cfg(feature = "std")]
+impl< W: io::Write > Output for IoWriter< W > {
+  #[inline]
+  fn write_bytes( &mut self, xs: &[u8] ) -> Result< (), Error > {
+    if self.depth > 0 {
+      self.buf.extend_from_slice( xs );
+      Ok( () )
+    } else {
+      self.inner.write_all( xs ).map_err( Error::Io )
+    }
+  }
+
+  #[inline]
+  fn begin_section( &mut self ) -> usize {
+    self.depth += 1;
+    self.buf.len( )
+  }
+
+  #[inline]
+  fn patch( &mut self, pos: usize, xs: &[u8] ) -> Result< (), Error > {
+    Output::patch( &mut self.buf, pos, xs )
+  }
+
+  #[inline]
+  fn insert( &mut self, pos: usize, xs: &[u8] ) -> Result< (), Error > {
+    Output::insert( &mut self.buf, pos, xs )
+  }
+
+  #[inline]
+  fn end_section( &mut self ) -> Result< (), Error > {
+    self.depth -= 1;
+    if self.depth == 0 {
+      self.inner.write_all( &self.buf ).map_err( Error::Io )?;
+      self.buf.clear( );
+    }
+    Ok( () )
+  }
+}
@@ -0,0 +1,68 @@
+// stdlib imports
+#[cfg(feature = "alloc")]
+use core::fmt;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+
+/// A step from a value into one of its parts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Segment {
+  /// A struct field
+  Field( &'static str ),
+  /// The content of an enum variant
+  Variant( &'static str ),
+  /// An element of a sequence or tuple, or an entry of a map
+  Index( usize )
+}
+
+/// The location of a value within the (de)serialized value
+///
+/// Displays like `config.servers[3].name`.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Path {
+  /// Segments from the innermost outward, as errors are located while they
+  /// propagate outward
+  rev: Vec< Segment >
+}
+
+#[cfg(feature = "alloc")]
+impl Path {
+  /// The segments, from the root inward
+  #[inline]
+  pub fn segments( &self ) -> impl DoubleEndedIterator< Item = &Segment > {
+    self.rev.iter( ).rev( )
+  }
+
+  #[inline]
+  pub fn is_empty( &self ) -> bool {
+    self.rev.is_empty( )
+  }
+
+  /// Prepends a segment
+  #[inline]
+  pub(crate) fn push_front( &mut self, segment: Segment ) {
+    self.rev.push( segment );
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for Path {
+  fn fmt( &self, f: &mut fmt::Formatter< '_ > ) -> fmt::Result {
+    for ( i, segment ) in self.segments( ).enumerate( ) {
+      match segment {
+        Segment::Field( name ) | Segment::Variant( name ) => {
+          if i > 0 {
+            write!( f, "." )?;
+          }
+          write!( f, "{}", name )?;
+        },
+        Segment::Index( index ) => {
+          write!( f, "[{}]", index )?;
+        }
+      }
+    }
+    Ok( () )
+  }
+}
@@ -1,14 +1,254 @@
 // stdlib imports
-use std::fmt;
+use core::fmt;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
 // external library imports
 use serde::{ser, Serialize};
+// local imports
+use crate::config::{CharEncoding, Config, IntEncoding, IntWidth, zigzag_encode, zigzag_encode_128};
+use crate::envelope;
+use crate::output::Output;
+use crate::tag;
+#[cfg(feature = "alloc")]
+use crate::path::Path;
 
 
+/// Serializer into an `Output`, which is a `Vec< u8 >` by default
 #[derive(Default)]
-pub struct BinSerializer {
-  out: Vec< u8 >
+pub struct BinSerializer<
+  #[cfg(feature = "alloc")] W: Output = Vec< u8 >,
+  #[cfg(not(feature = "alloc"))] W: Output
+> {
+  out: W,
+  config: Config,
+  /// Current nesting depth, bounded by `Config::max_depth`
+  depth: usize
 }
 
+#[cfg(feature = "alloc")]
+impl BinSerializer {
+  #[inline]
+  pub fn new( config: Config ) -> Self {
+    BinSerializer::with_output( Vec::new( ), config )
+  }
+}
+
+impl< W: Output > BinSerializer< W > {
+  #[inline]
+  pub fn with_output( out: W, config: Config ) -> Self {
+    BinSerializer { out, config, depth: 0 }
+  }
+
+  #[inline]
+  pub fn into_output( self ) -> W {
+    self.out
+  }
+
+  /// Writes the header of `Config::envelope`, which must precede the value
+  pub fn write_envelope( &mut self ) -> Result< (), Error > {
+    self.out.write_bytes( &envelope::encode( &self.config ) )
+  }
+
+  /// Writes a fingerprint (see `crate::fingerprint`), which
+  /// `BinDeserializer::check_fingerprint` checks. It must precede the value,
+  /// but follow the envelope.
+  pub fn write_fingerprint( &mut self, fingerprint: u64 ) -> Result< (), Error > {
+    self.write_u64( fingerprint )
+  }
+
+  /// Enters a nested value
+  #[inline]
+  fn enter( &mut self ) -> Result< (), Error > {
+    if self.depth >= self.config.max_depth {
+      return Err( Error::DepthLimitExceeded );
+    }
+    self.depth += 1;
+    Ok( () )
+  }
+
+  /// Leaves a nested value, entered with `enter`
+  #[inline]
+  fn leave( &mut self ) {
+    self.depth -= 1;
+  }
+
+  /// Leaves the current nested value if `res` failed. After an error, serde
+  /// abandons a compound without calling `end`, so its level is left here.
+  #[inline]
+  fn unwind< T >( &mut self, res: Result< T, Error > ) -> Result< T, Error > {
+    if res.is_err( ) {
+      self.leave( );
+    }
+    res
+  }
+
+  /// Writes `v` as an unsigned LEB128 varint
+  #[inline]
+  fn write_varint( &mut self, v: u64 ) -> Result< (), Error > {
+    let mut buf = [0u8; 10];
+    let n = encode_varint( v, &mut buf );
+    self.out.write_bytes( &buf[ ..n ] )
+  }
+
+  /// 128-bit variant of `write_varint`. Kept separate, such that the common
+  /// 64-bit path avoids 128-bit arithmetic.
+  #[inline]
+  fn write_varint_128( &mut self, mut v: u128 ) -> Result< (), Error > {
+    let mut buf = [0u8; 19];
+    let mut n = 0;
+    while v >= 0x80 {
+      buf[ n ] = ( v as u8 ) | 0x80;
+      v >>= 7;
+      n += 1;
+    }
+    buf[ n ] = v as u8;
+    self.out.write_bytes( &buf[ ..n + 1 ] )
+  }
+
+  /// Encodes `v` in the given width. The caller ensures it fits. Returns the
+  /// buffer and the number of bytes used.
+  #[inline]
+  fn encode_int( &self, v: u64, width: IntWidth ) -> ( [u8; 10], usize ) {
+    let mut buf = [0u8; 10];
+    let byte_order = self.config.byte_order;
+    let n = match width {
+      IntWidth::U8 => {
+        buf[ 0 ] = v as u8;
+        1
+      },
+      IntWidth::U16 => {
+        buf[ ..2 ].copy_from_slice( &byte_order.encode_u16( v as u16 ) );
+        2
+      },
+      IntWidth::U32 => {
+        buf[ ..4 ].copy_from_slice( &byte_order.encode_u32( v as u32 ) );
+        4
+      },
+      IntWidth::U64 => {
+        buf[ ..8 ].copy_from_slice( &byte_order.encode_u64( v ) );
+        8
+      },
+      IntWidth::Varint => encode_varint( v, &mut buf )
+    };
+    ( buf, n )
+  }
+
+  /// Writes `v` in the given width. The caller ensures it fits.
+  #[inline]
+  fn write_int( &mut self, v: u64, width: IntWidth ) -> Result< (), Error > {
+    match width {
+      IntWidth::U8     => self.out.write_u8( v as u8 ),
+      IntWidth::U16    => self.write_u16( v as u16 ),
+      IntWidth::U32    => self.write_u32( v as u32 ),
+      IntWidth::U64    => self.write_u64( v ),
+      IntWidth::Varint => self.write_varint( v )
+    }
+  }
+
+  /// Writes a length prefix, or returns `err` if it does not fit in `width`
+  #[inline]
+  fn write_len( &mut self, len: usize, width: IntWidth, err: Error ) -> Result< (), Error > {
+    let len = len as u64;
+    if len > width.max( ) {
+      return Err( err );
+    }
+    self.write_int( len, width )
+  }
+
+  /// With `Config::self_describing`, writes the type tag of a value
+  #[inline]
+  fn write_tag( &mut self, tag: u8 ) -> Result< (), Error > {
+    if self.config.self_describing {
+      return self.write_tag_cold( tag );
+    }
+    Ok( () )
+  }
+
+  /// Out of line, such that the default encoding is not slowed down by it
+  #[cold]
+  fn write_tag_cold( &mut self, tag: u8 ) -> Result< (), Error > {
+    self.out.write_u8( tag )
+  }
+
+  /// Writes a tag, followed by a length prefix. Only used when self-describing.
+  #[cold]
+  fn write_tagged_len( &mut self, tag: u8, len: usize, width: IntWidth, err: Error ) -> Result< (), Error > {
+    self.out.write_u8( tag )?;
+    self.write_len( len, width, err )
+  }
+
+  /// Writes a field or variant name as a tagged string. Only used when
+  /// self-describing.
+  #[cold]
+  fn write_name( &mut self, name: &str ) -> Result< (), Error > {
+    self.write_tagged_len( tag::STR, name.len( ), self.config.str_len, Error::ExceedStringLen )?;
+    self.out.write_bytes( name.as_bytes( ) )
+  }
+
+  /// Writes the start of a variant with content. When self-describing, this
+  /// is a map with a single entry, from its name to its content.
+  #[inline]
+  fn write_variant_start( &mut self, variant_index: u32, variant: &str ) -> Result< (), Error > {
+    if self.config.self_describing {
+      self.write_tagged_len( tag::MAP, 1, self.config.map_len, Error::ExceedMapLen )?;
+      self.write_name( variant )
+    } else {
+      self.write_variant( variant_index )
+    }
+  }
+
+  /// Writes an enum discriminant, in the configured width
+  #[inline]
+  fn write_variant( &mut self, variant_index: u32 ) -> Result< (), Error > {
+    let width = self.config.variant_width;
+    if variant_index as u64 > width.max( ) {
+      return Err( Error::ExceedEnumVariant );
+    }
+    self.write_int( variant_index as u64, width )
+  }
+
+  #[inline]
+  fn write_u16( &mut self, v: u16 ) -> Result< (), Error > {
+    self.out.write_bytes( &self.config.byte_order.encode_u16( v ) )
+  }
+
+  #[inline]
+  fn write_u32( &mut self, v: u32 ) -> Result< (), Error > {
+    self.out.write_bytes( &self.config.byte_order.encode_u32( v ) )
+  }
+
+  #[inline]
+  fn write_u64( &mut self, v: u64 ) -> Result< (), Error > {
+    self.out.write_bytes( &self.config.byte_order.encode_u64( v ) )
+  }
+
+  #[inline]
+  fn write_u128( &mut self, v: u128 ) -> Result< (), Error > {
+    self.out.write_bytes( &self.config.byte_order.encode_u128( v ) )
+  }
+}
+
+/// Encodes `v` as an unsigned LEB128 varint into `buf`. Returns the number of
+/// bytes used.
+#[inline]
+fn encode_varint( mut v: u64, buf: &mut [u8; 10] ) -> usize {
+  let mut n = 0;
+  while v >= 0x80 {
+    buf[ n ] = ( v as u8 ) | 0x80;
+    v >>= 7;
+    n += 1;
+  }
+  buf[ n ] = v as u8;
+  n + 1
+}
+
+#[cfg(feature = "alloc")]
 impl From< BinSerializer > for Vec< u8 > {
   #[inline]
   fn from( v: BinSerializer ) -> Self {
@@ -16,114 +256,156 @@ impl From< BinSerializer > for Vec< u8 > {
   }
 }
 
-impl< 'a > ser::Serializer for &'a mut BinSerializer {
+impl< 'a, W: Output > ser::Serializer for &'a mut BinSerializer< W > {
   type Ok = ();
 
   type Error = Error;
 
-  type SerializeSeq = Self;
+  type SerializeSeq = Compound< 'a, W >;
   type SerializeTuple = Self;
   type SerializeTupleStruct = Self;
   type SerializeTupleVariant = Self;
-  type SerializeMap = Self;
+  type SerializeMap = Compound< 'a, W >;
   type SerializeStruct = Self;
   type SerializeStructVariant = Self;
 
   #[inline]
   fn serialize_bool(self, v: bool) -> Result< Self::Ok, Self::Error > {
-    self.out.push( if v { 1 } else { 0 } );
-    Ok( () )
+    self.write_tag( tag::BOOL )?;
+    self.out.write_u8( v as u8 )
   }
 
   #[inline]
   fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
-    self.serialize_u8( unsafe { std::mem::transmute( v ) } )
+    self.write_tag( tag::I8 )?;
+    self.out.write_u8( v as u8 )
   }
 
   #[inline]
   fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
-    self.serialize_u16( unsafe { std::mem::transmute( v ) } )
+    self.write_tag( tag::I16 )?;
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => self.write_u16( v as u16 ),
+      IntEncoding::Varint => self.write_varint( zigzag_encode( v as i64 ) )
+    }
   }
 
   #[inline]
   fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
-    self.serialize_u32( unsafe { std::mem::transmute( v ) } )
+    self.write_tag( tag::I32 )?;
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => self.write_u32( v as u32 ),
+      IntEncoding::Varint => self.write_varint( zigzag_encode( v as i64 ) )
+    }
   }
 
   #[inline]
   fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
-    self.serialize_u64( unsafe { std::mem::transmute( v ) } )
+    self.write_tag( tag::I64 )?;
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => self.write_u64( v as u64 ),
+      IntEncoding::Varint => self.write_varint( zigzag_encode( v ) )
+    }
+  }
+
+  #[inline]
+  fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+    self.write_tag( tag::I128 )?;
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => self.write_u128( v as u128 ),
+      IntEncoding::Varint => self.write_varint_128( zigzag_encode_128( v ) )
+    }
   }
 
   #[inline]
   fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
-    self.out.push( v );
-    Ok( () )
+    self.write_tag( tag::U8 )?;
+    self.out.write_u8( v )
   }
 
   #[inline]
   fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
-    self.out.extend_from_slice( &v.to_ne_bytes( ) );
-    Ok( () )
+    self.write_tag( tag::U16 )?;
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => self.write_u16( v ),
+      IntEncoding::Varint => self.write_varint( v as u64 )
+    }
   }
 
   #[inline]
   fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
-    self.out.extend_from_slice( &v.to_ne_bytes( ) );
-    Ok( () )
+    self.write_tag( tag::U32 )?;
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => self.write_u32( v ),
+      IntEncoding::Varint => self.write_varint( v as u64 )
+    }
   }
 
   #[inline]
   fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
-    self.out.extend_from_slice( &v.to_ne_bytes( ) );
-    Ok( () )
+    self.write_tag( tag::U64 )?;
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => self.write_u64( v ),
+      IntEncoding::Varint => self.write_varint( v )
+    }
+  }
+
+  #[inline]
+  fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+    self.write_tag( tag::U128 )?;
+    match self.config.int_encoding {
+      IntEncoding::Fixed  => self.write_u128( v ),
+      IntEncoding::Varint => self.write_varint_128( v )
+    }
   }
 
   #[inline]
   fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
-    self.serialize_u32( unsafe { std::mem::transmute( v ) } )
+    self.write_tag( tag::F32 )?;
+    self.write_u32( v.to_bits( ) )
   }
 
   #[inline]
   fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
-    self.serialize_u64( unsafe { std::mem::transmute( v ) } )
+    self.write_tag( tag::F64 )?;
+    self.write_u64( v.to_bits( ) )
   }
 
   #[inline]
   fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+    self.write_tag( tag::CHAR )?;
+    if self.config.char_encoding == CharEncoding::CodePoint {
+      return match self.config.int_encoding {
+        IntEncoding::Fixed  => self.write_u32( v as u32 ),
+        IntEncoding::Varint => self.write_varint( v as u64 )
+      };
+    }
     let mut buffer = [0u8; 4];
     let buffer_slice = v.encode_utf8( &mut buffer );
     let num_bytes = buffer_slice.len( );
     // drop: buffer_slice
-    self.out.extend_from_slice( &buffer[0..num_bytes] );
-    Ok( () )
+    self.out.write_bytes( &buffer[0..num_bytes] )
   }
 
   #[inline]
   fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-    let len = v.len( ); // Length in *bytes*
-    if len > ( u32::MAX as usize ) {
-      return Err( Error::ExceedStringLen );
-    }
-    self.serialize_u32( len as u32 )?;
-    self.out.extend_from_slice( v.as_bytes( ) );
-    Ok( () )
+    self.write_tag( tag::STR )?;
+    // Length in *bytes*
+    self.write_len( v.len( ), self.config.str_len, Error::ExceedStringLen )?;
+    self.out.write_bytes( v.as_bytes( ) )
   }
 
   #[inline]
   fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
-    let len = v.len( ); // Length in *bytes*
-    if len > ( u64::MAX as usize ) {
-      return Err( Error::ExceedBytesLen );
-    }
-    self.serialize_u64( len as u64 )?;
-    self.out.extend_from_slice( v );
-    Ok( () )
+    self.write_tag( tag::BYTES )?;
+    self.write_len( v.len( ), self.config.bytes_len, Error::ExceedBytesLen )?;
+    self.out.write_bytes( v )
   }
 
   #[inline]
   fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
-    self.serialize_u8( 0 )
+    // When self-describing, the tag replaces the marker
+    self.out.write_u8( if self.config.self_describing { tag::NONE } else { 0 } )
   }
 
   #[inline]
@@ -131,13 +413,16 @@ impl< 'a > ser::Serializer for &'a mut BinSerializer {
     where
       T: ?Sized + Serialize {
 
-    self.serialize_u8( 1 )?;
-    value.serialize( self )
+    self.out.write_u8( if self.config.self_describing { tag::SOME } else { 1 } )?;
+    self.enter( )?;
+    let res = value.serialize( &mut *self );
+    self.leave( );
+    res
   }
 
   #[inline]
   fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
-    Ok( () )
+    self.write_tag( tag::UNIT )
   }
 
   #[inline]
@@ -150,14 +435,12 @@ impl< 'a > ser::Serializer for &'a mut BinSerializer {
     self,
     _name: &'static str,
     variant_index: u32,
-    _variant: &'static str,
+    variant: &'static str,
   ) -> Result<Self::Ok, Self::Error> {
-    if variant_index > ( u8::MAX as u32 ) {
-      Err( Error::ExceedEnumVariant )
-    } else {
-      self.serialize_u8( variant_index as u8 )?;
-      Ok( () )
+    if self.config.self_describing {
+      return self.write_name( variant );
     }
+    self.write_variant( variant_index )
   }
 
   #[inline]
@@ -177,37 +460,35 @@ impl< 'a > ser::Serializer for &'a mut BinSerializer {
       self,
       _name: &'static str,
       variant_index: u32,
-      _variant: &'static str,
+      variant: &'static str,
       value: &T,
   ) -> Result<Self::Ok, Self::Error>
     where
       T: ?Sized + Serialize {
 
-    if variant_index > ( u8::MAX as u32 ) {
-      Err( Error::ExceedEnumVariant )
-    } else {
-      self.serialize_u8( variant_index as u8 )?;
-      value.serialize( self )
-    }
+    self.write_variant_start( variant_index, variant )?;
+    self.enter( )?;
+    let res = value.serialize( &mut *self );
+    self.leave( );
+    res
   }
 
   #[inline]
   fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-    if let Some( len ) = len {
-      if len <= ( u32::MAX as usize ) {
-        self.serialize_u32( len as u32 )?;
-        Ok( self )
-      } else {
-        Err( Error::ExceedSeqLen )
-      }
-    } else {
-      Err( Error::UnknownSeqLen )
-    }
+    self.write_tag( tag::SEQ )?;
+    let width = self.config.seq_len;
+    let compound = Compound::begin( self, len, width, Error::ExceedSeqLen )?;
+    compound.ser.enter( )?;
+    Ok( compound )
   }
 
   #[inline]
-  fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+  fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
     // Note that the tuple length is statically known
+    if self.config.self_describing {
+      self.write_tagged_len( tag::SEQ, len, self.config.seq_len, Error::ExceedSeqLen )?;
+    }
+    self.enter( )?;
     Ok( self )
   }
 
@@ -215,10 +496,9 @@ impl< 'a > ser::Serializer for &'a mut BinSerializer {
   fn serialize_tuple_struct(
     self
   , _name: &'static str
-  , _len: usize
+  , len: usize
   ) -> Result<Self::SerializeTupleStruct, Self::Error> {
-    // Note that the tuple length is statically known
-    Ok( self )
+    self.serialize_tuple( len )
   }
 
   #[inline]
@@ -226,38 +506,38 @@ impl< 'a > ser::Serializer for &'a mut BinSerializer {
     self
   , _name: &'static str
   , variant_index: u32
-  , _variant: &'static str
-  , _len: usize,
+  , variant: &'static str
+  , len: usize,
   ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-    if variant_index > ( u8::MAX as u32 ) {
-      Err( Error::ExceedEnumVariant )
-    } else {
-      self.serialize_u8( variant_index as u8 )?;
-      // Note that the tuple length is statically known
-      Ok( self )
+    self.write_variant_start( variant_index, variant )?;
+    // Note that the tuple length is statically known
+    if self.config.self_describing {
+      self.write_tagged_len( tag::SEQ, len, self.config.seq_len, Error::ExceedSeqLen )?;
     }
+    self.enter( )?;
+    Ok( self )
   }
 
   #[inline]
   fn serialize_map( self, len: Option<usize> ) -> Result<Self::SerializeMap, Self::Error> {
-    if let Some( len ) = len {
-      if len <= u32::MAX as usize {
-        self.serialize_u32( len as u32 )?;
-        Ok( self )
-      } else {
-        Err( Error::ExceedMapLen )
-      }
-    } else {
-      Err( Error::UnknownMapLen )
-    }
+    self.write_tag( tag::MAP )?;
+    let width = self.config.map_len;
+    let compound = Compound::begin( self, len, width, Error::ExceedMapLen )?;
+    compound.ser.enter( )?;
+    Ok( compound )
   }
 
   #[inline]
   fn serialize_struct(
     self,
     _name: &'static str,
-    _len: usize,
+    len: usize,
   ) -> Result<Self::SerializeStruct, Self::Error> {
+    // When self-describing, structs are maps keyed by field name
+    if self.config.self_describing {
+      self.write_tagged_len( tag::MAP, len, self.config.map_len, Error::ExceedMapLen )?;
+    }
+    self.enter( )?;
     Ok( self )
   }
 
@@ -266,19 +546,135 @@ impl< 'a > ser::Serializer for &'a mut BinSerializer {
     self,
     _name: &'static str,
     variant_index: u32,
-    _variant: &'static str,
-    _len: usize,
+    variant: &'static str,
+    len: usize,
   ) -> Result<Self::SerializeStructVariant, Self::Error> {
-    if variant_index > ( u8::MAX as u32 ) {
-      Err( Error::ExceedEnumVariant )
+    self.write_variant_start( variant_index, variant )?;
+    if self.config.self_describing {
+      self.write_tagged_len( tag::MAP, len, self.config.map_len, Error::ExceedMapLen )?;
+    }
+    self.enter( )?;
+    Ok( self )
+  }
+
+  #[inline]
+  fn is_human_readable( &self ) -> bool {
+    self.config.human_readable
+  }
+
+  /// Without `alloc`, serde cannot format `value` into a `String` first.
+  /// Instead, it is formatted directly into the output, and its length prefix
+  /// is back-patched as for a sequence of unknown length.
+  #[cfg(not(feature = "alloc"))]
+  fn collect_str<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+      T: ?Sized + fmt::Display {
+    use core::fmt::Write;
+    self.write_tag( tag::STR )?;
+    let width = self.config.str_len;
+    let compound = Compound::begin( self, None, width, Error::ExceedStringLen )?;
+    let mut w = StrWriter { compound, err: None };
+    if write!( w, "{}", value ).is_err( ) {
+      return Err( w.err.unwrap_or( Error::Custom ) );
+    }
+    w.compound.finish( )?;
+    Ok( () )
+  }
+}
+
+/// Formats a string into a `Compound`, counting its bytes as its elements
+#[cfg(not(feature = "alloc"))]
+struct StrWriter< 'a, W: Output > {
+  compound: Compound< 'a, W >,
+  /// The output error that interrupted formatting, if any
+  err: Option< Error >
+}
+
+#[cfg(not(feature = "alloc"))]
+impl< W: Output > fmt::Write for StrWriter< '_, W > {
+  #[inline]
+  fn write_str( &mut self, s: &str ) -> fmt::Result {
+    if let Err( err ) = self.compound.ser.out.write_bytes( s.as_bytes( ) ) {
+      self.err = Some( err );
+      return Err( fmt::Error );
+    }
+    if let Some( p ) = &mut self.compound.pending {
+      p.count += s.len( );
+    }
+    Ok( () )
+  }
+}
+
+/// Serializer of sequences and maps, whose length may be unknown up front
+///
+/// When the length is unknown, a slot for the length prefix is reserved in
+/// the output and back-patched once all elements are serialized. The output is
+/// identical to that of a collection with known length.
+pub struct Compound< 'a, W: Output > {
+  ser: &'a mut BinSerializer< W >,
+  pending: Option< Pending >
+}
+
+/// A length prefix that still has to be written
+struct Pending {
+  /// Position of the reserved slot in the output
+  pos: usize,
+  /// Number of elements (or entries) serialized so far
+  count: usize,
+  width: IntWidth,
+  /// Returned when `count` does not fit in `width`
+  err: Error
+}
+
+impl< 'a, W: Output > Compound< 'a, W > {
+  #[inline]
+  fn begin( ser: &'a mut BinSerializer< W >, len: Option< usize >, width: IntWidth, err: Error ) -> Result< Self, Error > {
+    if let Some( len ) = len {
+      ser.write_len( len, width, err )?;
+      Ok( Compound { ser, pending: None } )
     } else {
-      self.serialize_u8( variant_index as u8 )?;
-      Ok( self )
+      let pos = ser.out.begin_section( );
+      // A varint's width is unknown until its value is, so it is inserted
+      // afterward instead.
+      let reserved = match width {
+        IntWidth::U8     => 1,
+        IntWidth::U16    => 2,
+        IntWidth::U32    => 4,
+        IntWidth::U64    => 8,
+        IntWidth::Varint => 0
+      };
+      ser.out.write_bytes( &[0u8; 8][ ..reserved ] )?;
+      Ok( Compound { ser, pending: Some( Pending { pos, count: 0, width, err } ) } )
     }
   }
+
+  #[inline]
+  fn count( &mut self ) {
+    if let Some( p ) = &mut self.pending {
+      p.count += 1;
+    }
+  }
+
+  /// Writes the pending length prefix, if any
+  #[inline]
+  fn finish( &mut self ) -> Result< (), Error > {
+    if let Some( p ) = self.pending.take( ) {
+      if p.count as u64 > p.width.max( ) {
+        return Err( p.err );
+      }
+      let ( buf, n ) = self.ser.encode_int( p.count as u64, p.width );
+      if p.width == IntWidth::Varint {
+        self.ser.out.insert( p.pos, &buf[ ..n ] )?;
+      } else {
+        self.ser.out.patch( p.pos, &buf[ ..n ] )?;
+      }
+      self.ser.out.end_section( )?;
+    }
+    Ok( () )
+  }
 }
 
-impl<'a> ser::SerializeSeq for &'a mut BinSerializer {
+impl< W: Output > ser::SerializeSeq for Compound< '_, W > {
   type Ok = ();
   type Error = Error;
 
@@ -286,16 +682,20 @@ impl<'a> ser::SerializeSeq for &'a mut BinSerializer {
   fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
       T: ?Sized + Serialize {
-    value.serialize( &mut **self )
+    self.count( );
+    let res = value.serialize( &mut *self.ser );
+    self.ser.unwind( res )
   }
 
   #[inline]
-  fn end(self) -> Result<Self::Ok, Self::Error> {
-    Ok( () )
+  fn end(mut self) -> Result<Self::Ok, Self::Error> {
+    let res = self.finish( );
+    self.ser.leave( );
+    res
   }
 }
 
-impl<'a> ser::SerializeTuple for &'a mut BinSerializer {
+impl< W: Output > ser::SerializeTuple for &mut BinSerializer< W > {
   type Ok = ();
   type Error = Error;
   
@@ -303,16 +703,18 @@ impl<'a> ser::SerializeTuple for &'a mut BinSerializer {
   fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
       T: ?Sized + Serialize {
-    value.serialize( &mut **self )
+    let res = value.serialize( &mut **self );
+    self.unwind( res )
   }
   
   #[inline]
   fn end(self) -> Result<Self::Ok, Self::Error> {
+    self.leave( );
     Ok( () )
   }
 }
 
-impl<'a> ser::SerializeTupleStruct for &'a mut BinSerializer {
+impl< W: Output > ser::SerializeTupleStruct for &mut BinSerializer< W > {
   type Ok = ();
   type Error = Error;
   
@@ -320,16 +722,18 @@ impl<'a> ser::SerializeTupleStruct for &'a mut BinSerializer {
   fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
       T: ?Sized + Serialize {
-    value.serialize( &mut **self )
+    let res = value.serialize( &mut **self );
+    self.unwind( res )
   }
   
   #[inline]
   fn end(self) -> Result<Self::Ok, Self::Error> {
+    self.leave( );
     Ok( () )
   }
 }
 
-impl<'a> ser::SerializeTupleVariant for &'a mut BinSerializer {
+impl< W: Output > ser::SerializeTupleVariant for &mut BinSerializer< W > {
   type Ok = ();
   type Error = Error;
   
@@ -337,16 +741,18 @@ impl<'a> ser::SerializeTupleVariant for &'a mut BinSerializer {
   fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
       T: ?Sized + Serialize {
-    value.serialize( &mut **self )
+    let res = value.serialize( &mut **self );
+    self.unwind( res )
   }
   
   #[inline]
   fn end(self) -> Result<Self::Ok, Self::Error> {
+    self.leave( );
     Ok( () )
   }
 }
 
-impl<'a> ser::SerializeMap for &'a mut BinSerializer {
+impl< W: Output > ser::SerializeMap for Compound< '_, W > {
   type Ok = ();
   type Error = Error;
 
@@ -354,52 +760,69 @@ impl<'a> ser::SerializeMap for &'a mut BinSerializer {
   fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
     where
       T: ?Sized + Serialize {
-    key.serialize( &mut **self )
+    self.count( );
+    let res = key.serialize( &mut *self.ser );
+    self.ser.unwind( res )
   }
 
   #[inline]
   fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
       T: ?Sized + Serialize {
-    value.serialize( &mut **self )
+    let res = value.serialize( &mut *self.ser );
+    self.ser.unwind( res )
   }
 
   #[inline]
-  fn end(self) -> Result<Self::Ok, Self::Error> {
-    Ok( () )
+  fn end(mut self) -> Result<Self::Ok, Self::Error> {
+    let res = self.finish( );
+    self.ser.leave( );
+    res
   }
 }
 
-impl<'a> ser::SerializeStruct for &'a mut BinSerializer {
+impl< W: Output > ser::SerializeStruct for &mut BinSerializer< W > {
   type Ok = ();
   type Error = Error;
 
   #[inline]
-  fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<(), Self::Error>
+  fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
       T: ?Sized + Serialize {
-    value.serialize( &mut **self )
+    if self.config.self_describing {
+      let res = self.write_name( key );
+      self.unwind( res )?;
+    }
+    let res = value.serialize( &mut **self );
+    self.unwind( res )
   }
 
   #[inline]
   fn end(self) -> Result<Self::Ok, Self::Error> {
+    self.leave( );
     Ok( () )
   }
 }
 
-impl<'a> ser::SerializeStructVariant for &'a mut BinSerializer {
+impl< W: Output > ser::SerializeStructVariant for &mut BinSerializer< W > {
   type Ok = ();
   type Error = Error;
 
   #[inline]
-  fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<(), Self::Error>
+  fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
       T: ?Sized + Serialize {
-    value.serialize( &mut **self )
+    if self.config.self_describing {
+      let res = self.write_name( key );
+      self.unwind( res )?;
+    }
+    let res = value.serialize( &mut **self );
+    self.unwind( res )
   }
 
   #[inline]
   fn end(self) -> Result<Self::Ok, Self::Error> {
+    self.leave( );
     Ok( () )
   }
 }
@@ -415,24 +838,65 @@ impl<'a> ser::SerializeStructVariant for &'a mut BinSerializer {
 /// differently.
 #[derive(Debug)]
 pub enum Error {
-  /// The length of a string exceeds 2^32-1
+  /// The length of a string exceeds its configured width
   ExceedStringLen,
-  /// The length of a map exceeds 2^32-1
+  /// The length of a map exceeds its configured width
   ExceedMapLen,
-  /// The length of a byte array exceeds 2^64-1
+  /// The length of a byte array exceeds its configured width
   ExceedBytesLen,
-  /// The enum variant index exceeded 2^8-1
+  /// The enum variant index exceeds its configured width
   ExceedEnumVariant,
-  /// The sequence length exceeds 2^32-1
+  /// The sequence length exceeds its configured width
   ExceedSeqLen,
+  /// No longer produced, as sequences of unknown length are supported
   UnknownSeqLen,
+  /// No longer produced, as maps of unknown length are supported
   UnknownMapLen,
-  Custom( String )
+  /// Writing to the underlying `std::io::Write` failed
+  #[cfg(feature = "std")]
+  Io( io::Error ),
+  /// The value does not fit in the output buffer
+  BufferTooSmall,
+  /// The value is nested deeper than `Config::max_depth`
+  DepthLimitExceeded,
+  /// (`FrameWriter`) The value is longer than the maximum frame length
+  FrameTooLarge,
+  /// (Typed) The shape of the type cannot be traced, as it rejects every
+  /// placeholder value
+  UntraceableType,
+  /// (With `Config::error_context`) An error, with where it occurred
+  #[cfg(feature = "alloc")]
+  Context( Box< Context > ),
+  #[cfg(feature = "alloc")]
+  Custom( String ),
+  /// Without `alloc`, custom error messages cannot be stored
+  #[cfg(not(feature = "alloc"))]
+  Custom
+}
+
+/// Where a serialization error occurred
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct Context {
+  pub path: Path,
+  pub error: Error
+}
+
+#[cfg(feature = "alloc")]
+impl Error {
+  /// The error itself, without its `Context`
+  #[inline]
+  pub fn inner( &self ) -> &Error {
+    match self {
+      Error::Context( context ) => &context.error,
+      err => err
+    }
+  }
 }
 
 impl fmt::Display for Error {
   #[inline]
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
       Error::ExceedStringLen => {
         write!( f, "ExceedStringLen" )
@@ -455,18 +919,64 @@ impl fmt::Display for Error {
       Error::UnknownMapLen => {
         write!( f, "UnknownMapLen" )
       },
+      #[cfg(feature = "std")]
+      Error::Io( err ) => {
+        write!( f, "Io: {}", err )
+      },
+      Error::BufferTooSmall => {
+        write!( f, "BufferTooSmall" )
+      },
+      Error::DepthLimitExceeded => {
+        write!( f, "DepthLimitExceeded" )
+      },
+      Error::FrameTooLarge => {
+        write!( f, "FrameTooLarge" )
+      },
+      Error::UntraceableType => {
+        write!( f, "UntraceableType" )
+      },
+      #[cfg(feature = "alloc")]
+      Error::Context( context ) => {
+        write!( f, "{} at {}", context.error, context.path )
+      },
+      #[cfg(feature = "alloc")]
       Error::Custom( msg ) => {
         write!( f, "{}", msg )
+      },
+      #[cfg(not(feature = "alloc"))]
+      Error::Custom => {
+        write!( f, "Custom" )
       }
     }
   }
 }
 
-impl std::error::Error for Error { }
+/// With `std`, this is `std::error::Error`
+impl core::error::Error for Error {
+  #[inline]
+  fn source( &self ) -> Option< &( dyn core::error::Error + 'static ) > {
+    match self {
+      #[cfg(feature = "std")]
+      Error::Io( err ) => Some( err ),
+      #[cfg(feature = "alloc")]
+      Error::Context( context ) => context.error.source( ),
+      _ => None
+    }
+  }
+}
 
+#[cfg(feature = "alloc")]
 impl ser::Error for Error {
   #[inline]
   fn custom<T>( msg: T ) -> Self where T: fmt::Display {
     Error::Custom( msg.to_string( ) )
   }
 }
+
+#[cfg(not(feature = "alloc"))]
+impl ser::Error for Error {
+  #[inline]
+  fn custom<T>( _msg: T ) -> Self where T: fmt::Display {
+    Error::Custom
+  }
+}
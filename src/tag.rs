@@ -0,0 +1,30 @@
+//! Type tags of the self-describing encoding (see `Config::self_describing`)
+//!
+//! Every value is preceded by one of these, followed by its regular encoding.
+//! Options are encoded by their tag alone, instead of a marker byte. Newtype
+//! structs are encoded as their content, unit structs as `UNIT`, tuples as
+//! `SEQ`, and structs as `MAP`s keyed by their field names. Unit variants are
+//! encoded as the `STR` of their name, and other variants as a `MAP` with a
+//! single entry from their name to their content.
+
+pub(crate) const UNIT: u8  = 0;
+pub(crate) const BOOL: u8  = 1;
+pub(crate) const I8: u8    = 2;
+pub(crate) const I16: u8   = 3;
+pub(crate) const I32: u8   = 4;
+pub(crate) const I64: u8   = 5;
+pub(crate) const I128: u8  = 6;
+pub(crate) const U8: u8    = 7;
+pub(crate) const U16: u8   = 8;
+pub(crate) const U32: u8   = 9;
+pub(crate) const U64: u8   = 10;
+pub(crate) const U128: u8  = 11;
+pub(crate) const F32: u8   = 12;
+pub(crate) const F64: u8   = 13;
+pub(crate) const CHAR: u8  = 14;
+pub(crate) const STR: u8   = 15;
+pub(crate) const BYTES: u8 = 16;
+pub(crate) const NONE: u8  = 17;
+pub(crate) const SOME: u8  = 18;
+pub(crate) const SEQ: u8   = 19;
+pub(crate) const MAP: u8   = 20;
@@ -1,13 +1,17 @@
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
+  // stdlib imports, which are not in the prelude of a `no_std` crate
+  #[cfg(not(feature = "std"))]
+  use std::{borrow::ToOwned, boxed::Box, format, string::{String, ToString}, vec, vec::Vec};
   use serde::Deserialize;
 // external library imports
   use ::serde::Serialize;
   // local imports
-  use crate::{BinSerializer, BinDeserializer};
+  use crate::{BinSerializer, BinDeserializer, ByteOrder, Config, IntWidth, de, ser};
 
   #[test]
+  #[allow(clippy::unit_cmp)]
   pub fn test_unit( ) {
     let v = ();
     let v_data: [u8; 0] = [];
@@ -155,6 +159,90 @@ mod tests {
     assert_eq!( bin_deserialize::< Foo >( &constr12_ser ), constr12_raw );
   }
 
+  #[test]
+  pub fn test_struct_variant( ) {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum E { V { a: u32, b: u32 } }
+
+    let v = E::V { a: 0x1234_5678, b: 0xBABE_EBAB };
+    let v_ser = concat::< _, 1, 8, 9 >( [0], concat::< _, 4, 4, 8 >( 0x1234_5678u32.to_ne_bytes( ), 0xBABE_EBABu32.to_ne_bytes( ) ) );
+
+    // # Serialize
+
+    assert_eq!( bin_serialize( &v ), v_ser );
+
+    // # Deserialize
+
+    assert_eq!( bin_deserialize::< E >( &v_ser ), v );
+  }
+
+  #[test]
+  pub fn test_nested_variants( ) {
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    enum Shape {
+      Empty,
+      Circle( f32 ),
+      Line( i16, i16 ),
+      Rect { w: u8, h: u64 }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Scene {
+      first: Shape,
+      all: Vec< Shape >,
+      maybe: Option< Shape >,
+      nested: Vec< Option< ( Shape, Shape ) > >,
+      last: Shape
+    }
+
+    let shapes = [ Shape::Empty, Shape::Circle( 1.5 ), Shape::Line( -1, 3 ), Shape::Rect { w: 4, h: 5 } ];
+
+    for ( i, shape ) in shapes.iter( ).enumerate( ) {
+      let v = Scene {
+        first: shape.clone( ),
+        all: shapes.to_vec( ),
+        maybe: if i % 2 == 0 { Some( shape.clone( ) ) } else { None },
+        nested: vec![ None, Some( ( shape.clone( ), Shape::Empty ) ), Some( ( Shape::Rect { w: 1, h: 2 }, shape.clone( ) ) ) ],
+        last: shapes[ 3 - i ].clone( )
+      };
+      for config in [ Config::default( ), Config::varint( ) ] {
+        let data = crate::serialize_with( &v, config ).unwrap( );
+        assert_eq!( crate::deserialize_with::< Scene >( &data, config ).unwrap( ), v );
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_enum_variant_width( ) {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Foo { A, B( u8 ) }
+
+    // # Serialize
+
+    let config = Config { variant_width: IntWidth::U16, byte_order: ByteOrder::Big, ..Config::default( ) };
+    assert_eq!( crate::serialize_with( &Foo::B( 7 ), config ).unwrap( ), [ 0, 1, 7 ] );
+    assert_eq!( crate::serialize_with( &Opcode( 300 ), config ).unwrap( ), [ 0x01, 0x2C ] );
+    assert!( matches!( crate::serialize_with( &Opcode( 300 ), Config::default( ) ), Err( ser::Error::ExceedEnumVariant ) ) );
+    assert_eq!( crate::serialize_with( &Opcode( 300 ), Config::varint( ) ).unwrap( ), [ 0xAC, 0x02 ] );
+
+    // # Deserialize
+
+    for config in [ config, Config::varint( ), Config { variant_width: IntWidth::U32, ..config } ] {
+      for v in [ 0, 255, 256, 299 ] {
+        let data = crate::serialize_with( &Opcode( v ), config ).unwrap( );
+        assert_eq!( crate::deserialize_with::< Opcode >( &data, config ).unwrap( ), Opcode( v ) );
+      }
+      let data = crate::serialize_with( &Foo::B( 7 ), config ).unwrap( );
+      assert_eq!( crate::deserialize_with::< Foo >( &data, config ).unwrap( ), Foo::B( 7 ) );
+    }
+
+    // # Unknown variant
+
+    assert!( matches!( crate::deserialize::< Foo >( &[ 2 ] ), Err( de::Error::UnknownEnumVariant ) ) );
+    let data = crate::serialize_with( &300u16, config ).unwrap( );
+    assert!( matches!( crate::deserialize_with::< Opcode >( &data, config ), Err( de::Error::UnknownEnumVariant ) ) );
+  }
+
   #[test]
   pub fn test_vec_u32( ) {
     let xs = vec![ 1u32, 2, 3 ];
@@ -198,8 +286,1489 @@ mod tests {
     assert_eq!( &bin_deserialize::< Vec< u32 > >( &xs_ser ), xs );
   }
 
+  #[test]
+  pub fn test_byte_order( ) {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Mixed { a: u16, b: i32, c: f64, d: Vec< u64 > }
+
+    let little = Config { byte_order: ByteOrder::Little, ..Config::default( ) };
+    let big    = Config { byte_order: ByteOrder::Big, ..Config::default( ) };
+
+    // # Serialize
+
+    assert_eq!( crate::serialize_with( &0x1234_5678u32, little ).unwrap( ), [ 0x78, 0x56, 0x34, 0x12 ] );
+    assert_eq!( crate::serialize_with( &0x1234_5678u32, big ).unwrap( ), [ 0x12, 0x34, 0x56, 0x78 ] );
+
+    let mut vec_ser = vec![ 0, 0, 0, 2 ];
+    vec_ser.extend_from_slice( &1u64.to_be_bytes( ) );
+    vec_ser.extend_from_slice( &2u64.to_be_bytes( ) );
+    assert_eq!( crate::serialize_with( &vec![ 1u64, 2 ], big ).unwrap( ), vec_ser );
+
+    // # Deserialize
+
+    let v = Mixed { a: 0xABCD, b: -2, c: 1.5, d: vec![ 7, 0xFFFF_0000_0000 ] };
+    for config in [ little, big, Config::default( ) ] {
+      let data = crate::serialize_with( &v, config ).unwrap( );
+      assert_eq!( crate::deserialize_with::< Mixed >( &data, config ).unwrap( ), v );
+    }
+    assert_eq!( crate::deserialize_with::< u16 >( &[ 0x12, 0x34 ], big ).unwrap( ), 0x1234 );
+    assert_eq!( crate::deserialize_with::< u16 >( &[ 0x12, 0x34 ], little ).unwrap( ), 0x3412 );
+  }
+
+  #[test]
+  pub fn test_varint( ) {
+    let config = Config::varint( );
+
+    // # Serialize
+
+    assert_eq!( crate::serialize_with( &0u32, config ).unwrap( ), [ 0x00 ] );
+    assert_eq!( crate::serialize_with( &127u64, config ).unwrap( ), [ 0x7F ] );
+    assert_eq!( crate::serialize_with( &300u16, config ).unwrap( ), [ 0xAC, 0x02 ] );
+    assert_eq!( crate::serialize_with( &u32::MAX, config ).unwrap( ), [ 0xFF, 0xFF, 0xFF, 0xFF, 0x0F ] );
+    assert_eq!( crate::serialize_with( &u64::MAX, config ).unwrap( ).len( ), 10 );
+    // length prefixes are varints too
+    assert_eq!( crate::serialize_with( &"abc", config ).unwrap( ), [ 3, b'a', b'b', b'c' ] );
+    assert_eq!( crate::serialize_with( &vec![ 1u32, 2 ], config ).unwrap( ), [ 2, 1, 2 ] );
+    // floats are never varints
+    assert_eq!( crate::serialize_with( &1.0f32, config ).unwrap( ), 1.0f32.to_ne_bytes( ) );
+
+    // # Deserialize
+
+    for v in [ 0u64, 1, 127, 128, 16383, 16384, u32::MAX as u64, u64::MAX - 1, u64::MAX ] {
+      let data = crate::serialize_with( &v, config ).unwrap( );
+      assert_eq!( crate::deserialize_with::< u64 >( &data, config ).unwrap( ), v );
+    }
+    let xs = vec![ ( 1u16, 2u32 ), ( u16::MAX, u32::MAX ) ];
+    let data = crate::serialize_with( &xs, config ).unwrap( );
+    assert_eq!( crate::deserialize_with::< Vec< ( u16, u32 ) > >( &data, config ).unwrap( ), xs );
+  }
+
+  #[test]
+  pub fn test_varint_zigzag( ) {
+    let config = Config::varint( );
+
+    // # Serialize
+
+    assert_eq!( crate::serialize_with( &0i32, config ).unwrap( ), [ 0x00 ] );
+    assert_eq!( crate::serialize_with( &-1i32, config ).unwrap( ), [ 0x01 ] );
+    assert_eq!( crate::serialize_with( &1i64, config ).unwrap( ), [ 0x02 ] );
+    assert_eq!( crate::serialize_with( &-64i16, config ).unwrap( ), [ 0x7F ] );
+    assert_eq!( crate::serialize_with( &64i16, config ).unwrap( ), [ 0x80, 0x01 ] );
+    assert_eq!( crate::serialize_with( &i32::MIN, config ).unwrap( ), [ 0xFF, 0xFF, 0xFF, 0xFF, 0x0F ] );
+    assert_eq!( crate::serialize_with( &i64::MAX, config ).unwrap( ).len( ), 10 );
+    // single bytes are not zigzag-mapped
+    assert_eq!( crate::serialize_with( &-1i8, config ).unwrap( ), [ 0xFF ] );
+
+    // # Deserialize
+
+    for v in [ i16::MIN, i16::MIN + 1, -1, 0, 1, i16::MAX - 1, i16::MAX ] {
+      let data = crate::serialize_with( &v, config ).unwrap( );
+      assert_eq!( crate::deserialize_with::< i16 >( &data, config ).unwrap( ), v );
+    }
+    for v in [ i32::MIN, i32::MIN + 1, -1, 0, 1, i32::MAX - 1, i32::MAX ] {
+      let data = crate::serialize_with( &v, config ).unwrap( );
+      assert_eq!( crate::deserialize_with::< i32 >( &data, config ).unwrap( ), v );
+    }
+    for v in [ i64::MIN, i64::MIN + 1, -1, 0, 1, i64::MAX - 1, i64::MAX ] {
+      let data = crate::serialize_with( &v, config ).unwrap( );
+      assert_eq!( crate::deserialize_with::< i64 >( &data, config ).unwrap( ), v );
+    }
+    for v in [ i8::MIN, -1, 0, i8::MAX ] {
+      let data = crate::serialize_with( &v, config ).unwrap( );
+      assert_eq!( crate::deserialize_with::< i8 >( &data, config ).unwrap( ), v );
+    }
+    // a ZigZag-mapped i16 never exceeds 2^16-1
+    assert!( matches!( crate::deserialize_with::< i16 >( &[ 0x80, 0x80, 0x04 ], config ), Err( de::Error::MalformedVarint ) ) );
+  }
+
+  #[test]
+  pub fn test_len_width( ) {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Lens { s: String, b: serde_bytes_like::Bytes, xs: Vec< u8 >, m: std::collections::BTreeMap< u8, u8 > }
+
+    let config = Config {
+      str_len:   IntWidth::U8,
+      bytes_len: IntWidth::U16,
+      seq_len:   IntWidth::Varint,
+      map_len:   IntWidth::U64,
+      byte_order: ByteOrder::Little,
+      ..Config::default( )
+    };
+
+    let v = Lens {
+      s: "ab".to_owned( ),
+      b: serde_bytes_like::Bytes( vec![ 9 ] ),
+      xs: vec![ 7; 200 ],
+      m: [ ( 1, 2 ) ].into_iter( ).collect( )
+    };
+
+    // # Serialize
+
+    let data = crate::serialize_with( &v, config ).unwrap( );
+    let mut expected = vec![ 2, b'a', b'b', 1, 0, 9, 0xC8, 0x01 ];
+    expected.extend_from_slice( &[ 7; 200 ] );
+    expected.extend_from_slice( &[ 1, 0, 0, 0, 0, 0, 0, 0, 1, 2 ] );
+    assert_eq!( data, expected );
+
+    // # Deserialize
+
+    assert_eq!( crate::deserialize_with::< Lens >( &data, config ).unwrap( ), v );
+
+    // # Exceeding the width
+
+    let long = "x".repeat( 256 );
+    assert!( matches!( crate::serialize_with( &long, config ), Err( ser::Error::ExceedStringLen ) ) );
+    assert!( crate::serialize_with( &&long[ ..255 ], config ).is_ok( ) );
+    let big = serde_bytes_like::Bytes( vec![ 0; 0x1_0000 ] );
+    assert!( matches!( crate::serialize_with( &big, config ), Err( ser::Error::ExceedBytesLen ) ) );
+    let config = Config { seq_len: IntWidth::U8, map_len: IntWidth::U16, ..config };
+    assert!( matches!( crate::serialize_with( &vec![ 0u8; 256 ], config ), Err( ser::Error::ExceedSeqLen ) ) );
+    let m: std::collections::BTreeMap< u32, () > = ( 0..0x1_0000 ).map( |i| ( i, () ) ).collect( );
+    assert!( matches!( crate::serialize_with( &m, config ), Err( ser::Error::ExceedMapLen ) ) );
+  }
+
+  #[test]
+  pub fn test_128( ) {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Money { uuid: u128, cents: i128 }
+
+    // # Serialize
+
+    let v = 0x0011_2233_4455_6677_8899_AABB_CCDD_EEFFu128;
+    assert_eq!( crate::serialize( &v ).unwrap( ), v.to_ne_bytes( ) );
+    assert_eq!( crate::serialize( &-2i128 ).unwrap( ), ( -2i128 ).to_ne_bytes( ) );
+    let big = Config { byte_order: ByteOrder::Big, ..Config::default( ) };
+    assert_eq!( crate::serialize_with( &v, big ).unwrap( ), v.to_be_bytes( ) );
+    assert_eq!( crate::serialize_with( &300u128, Config::varint( ) ).unwrap( ), [ 0xAC, 0x02 ] );
+    assert_eq!( crate::serialize_with( &-1i128, Config::varint( ) ).unwrap( ), [ 0x01 ] );
+    assert_eq!( crate::serialize_with( &u128::MAX, Config::varint( ) ).unwrap( ).len( ), 19 );
+
+    // # Deserialize
+
+    let m = Money { uuid: v, cents: -123_456_789_012_345_678_901_234 };
+    assert_eq!( crate::deserialize::< Money >( &crate::serialize( &m ).unwrap( ) ).unwrap( ), m );
+    for config in [ big, Config::varint( ) ] {
+      for v in [ 0, 1, u64::MAX as u128 + 1, u128::MAX ] {
+        let data = crate::serialize_with( &v, config ).unwrap( );
+        assert_eq!( crate::deserialize_with::< u128 >( &data, config ).unwrap( ), v );
+      }
+      for v in [ i128::MIN, -1, 0, 1, i128::MAX ] {
+        let data = crate::serialize_with( &v, config ).unwrap( );
+        assert_eq!( crate::deserialize_with::< i128 >( &data, config ).unwrap( ), v );
+      }
+    }
+
+    // exceeds 128 bits
+    let mut too_long = vec![ 0xFF; 18 ];
+    too_long.push( 0x04 );
+    assert!( matches!( crate::deserialize_with::< u128 >( &too_long, Config::varint( ) ), Err( de::Error::MalformedVarint ) ) );
+  }
+
+  #[test]
+  pub fn test_varint_malformed( ) {
+    let config = Config::varint( );
+
+    // overlong
+    assert!( matches!( crate::deserialize_with::< u32 >( &[ 0x80, 0x00 ], config ), Err( de::Error::MalformedVarint ) ) );
+    assert!( matches!( crate::deserialize_with::< u32 >( &[ 0x81, 0x80, 0x00 ], config ), Err( de::Error::MalformedVarint ) ) );
+    // exceeds the target type
+    assert!( matches!( crate::deserialize_with::< u16 >( &[ 0x80, 0x80, 0x04 ], config ), Err( de::Error::MalformedVarint ) ) );
+    assert!( matches!( crate::deserialize_with::< u32 >( &[ 0xFF, 0xFF, 0xFF, 0xFF, 0x1F ], config ), Err( de::Error::MalformedVarint ) ) );
+    // exceeds 64 bits
+    let too_long = [ 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x02 ];
+    assert!( matches!( crate::deserialize_with::< u64 >( &too_long, config ), Err( de::Error::MalformedVarint ) ) );
+    let too_long = [ 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x81, 0x00 ];
+    assert!( matches!( crate::deserialize_with::< u64 >( &too_long, config ), Err( de::Error::MalformedVarint ) ) );
+    // truncated
+    assert!( matches!( crate::deserialize_with::< u32 >( &[ 0x80 ], config ), Err( de::Error::InsufficientData ) ) );
+  }
+
+  #[test]
+  pub fn test_unknown_len( ) {
+    use std::collections::BTreeMap;
+
+    /// Serializes its elements without announcing their number up front
+    struct Unsized< T >( T );
+
+    impl< T: Serialize > Serialize for Unsized< Vec< T > > {
+      fn serialize< S: serde::Serializer >( &self, s: S ) -> Result< S::Ok, S::Error > {
+        s.collect_seq( self.0.iter( ).filter( |_| true ) )
+      }
+    }
+
+    impl< K: Serialize, V: Serialize > Serialize for Unsized< BTreeMap< K, V > > {
+      fn serialize< S: serde::Serializer >( &self, s: S ) -> Result< S::Ok, S::Error > {
+        s.collect_map( self.0.iter( ).filter( |_| true ) )
+      }
+    }
+
+    let xs: Vec< Vec< u32 > > = vec![ vec![ ], vec![ 1 ], vec![ 2, 3, 4 ] ];
+    let unsized_xs = Unsized( xs.iter( ).cloned( ).map( Unsized ).collect::< Vec< _ > >( ) );
+    let m: BTreeMap< String, Vec< u8 > > = [ ( "a".to_owned( ), vec![ 1 ] ), ( "bc".to_owned( ), vec![ ] ) ].into_iter( ).collect( );
+    let unsized_m = Unsized( m.clone( ) );
+
+    let widths = [ IntWidth::U8, IntWidth::U16, IntWidth::U32, IntWidth::U64, IntWidth::Varint ];
+    for width in widths {
+      let config = Config { seq_len: width, map_len: width, ..Config::default( ) };
+
+      // # Serialize
+
+      let data = crate::serialize_with( &unsized_xs, config ).unwrap( );
+      assert_eq!( data, crate::serialize_with( &xs, config ).unwrap( ) );
+      let m_data = crate::serialize_with( &unsized_m, config ).unwrap( );
+      assert_eq!( m_data, crate::serialize_with( &m, config ).unwrap( ) );
+
+      // # Deserialize
+
+      assert_eq!( crate::deserialize_with::< Vec< Vec< u32 > > >( &data, config ).unwrap( ), xs );
+      assert_eq!( crate::deserialize_with::< BTreeMap< String, Vec< u8 > > >( &m_data, config ).unwrap( ), m );
+    }
+
+    // a varint prefix grows once there are 128 elements
+    let long = Unsized( ( 0..300u16 ).collect::< Vec< _ > >( ) );
+    assert_eq!( crate::serialize_with( &long, Config::varint( ) ).unwrap( ), crate::serialize_with( &long.0, Config::varint( ) ).unwrap( ) );
+
+    // the length is still checked against its width
+    let config = Config { seq_len: IntWidth::U8, ..Config::default( ) };
+    let long = Unsized( vec![ 0u8; 256 ] );
+    assert!( matches!( crate::serialize_with( &long, config ), Err( ser::Error::ExceedSeqLen ) ) );
+  }
+
+  #[test]
+  pub fn test_borrowed( ) {
+    use std::borrow::Cow;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Borrowed< 'a > {
+      name: &'a str,
+      #[serde(with = "borrowed_bytes")]
+      payload: &'a [u8],
+      #[serde(borrow)]
+      label: Cow< 'a, str >,
+      owned: String
+    }
+
+    let v = Borrowed { name: "name", payload: &[ 1, 2, 3 ], label: Cow::Borrowed( "label" ), owned: "owned".to_owned( ) };
+    let data = crate::serialize( &v ).unwrap( );
+
+    // # Deserialize
+
+    let res: Borrowed = crate::from_slice( &data ).unwrap( );
+    assert_eq!( res, v );
+    let range = data.as_ptr_range( );
+    assert!( range.contains( &res.name.as_ptr( ) ) );
+    assert!( range.contains( &res.payload.as_ptr( ) ) );
+    assert!( matches!( res.label, Cow::Borrowed( _ ) ) );
+
+    let data = crate::serialize_with( &v, Config::varint( ) ).unwrap( );
+    let res: Borrowed = crate::from_slice_with( &data, Config::varint( ) ).unwrap( );
+    assert_eq!( res, v );
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  pub fn test_writer( ) {
+    use std::collections::BTreeMap;
+    use std::io;
+
+    #[derive(Serialize)]
+    struct Snapshot { id: u64, names: Vec< String >, index: BTreeMap< u32, Option< i16 > > }
+
+    /// Fails after accepting `remaining` bytes
+    struct Failing { remaining: usize }
+
+    impl io::Write for Failing {
+      fn write( &mut self, xs: &[u8] ) -> io::Result< usize > {
+        if xs.len( ) > self.remaining {
+          Err( io::Error::other( "disk full" ) )
+        } else {
+          self.remaining -= xs.len( );
+          Ok( xs.len( ) )
+        }
+      }
+
+      fn flush( &mut self ) -> io::Result< () > {
+        Ok( () )
+      }
+    }
+
+    let v = Snapshot {
+      id: 42,
+      names: vec![ "a".to_owned( ), "bcd".to_owned( ) ],
+      index: [ ( 1, Some( -1 ) ), ( 2, None ) ].into_iter( ).collect( )
+    };
+
+    // # Serialize
+
+    for config in [ Config::default( ), Config::varint( ) ] {
+      let mut out = Vec::new( );
+      crate::serialize_into_writer_with( &mut out, &v, config ).unwrap( );
+      assert_eq!( out, crate::serialize_with( &v, config ).unwrap( ) );
+    }
+
+    // unknown lengths are buffered, and written once complete
+    let nested: Vec< Vec< u8 > > = vec![ vec![ 1, 2 ], vec![ ], vec![ 3 ] ];
+    let mut out = Vec::new( );
+    let mut s = BinSerializer::with_output( crate::ser::IoWriter::new( &mut out ), Config::varint( ) );
+    serde::Serializer::collect_seq( &mut s, nested.iter( ).map( |xs| UnsizedSeq( xs ) ) ).unwrap( );
+    assert_eq!( out, crate::serialize_with( &nested, Config::varint( ) ).unwrap( ) );
+
+    // # I/O errors
+
+    let res = crate::serialize_into_writer( Failing { remaining: 10 }, &v );
+    assert!( matches!( res, Err( ser::Error::Io( ref err ) ) if err.to_string( ) == "disk full" ) );
+  }
+
+  #[test]
+  pub fn test_slice_output( ) {
+    #[derive(Serialize)]
+    struct Frame< 'a > { seq: u32, name: &'a str, xs: UnsizedSeq< 'a, u16 > }
+
+    let xs = [ 1u16, 2, 3 ];
+    let v = Frame { seq: 9, name: "frame", xs: UnsizedSeq( &xs ) };
+
+    // # Serialize
+
+    for config in [ Config::default( ), Config::varint( ) ] {
+      let expected = crate::serialize_with( &v, config ).unwrap( );
+      let mut buf = [0xAAu8; 64];
+      let n = crate::serialize_to_slice_with( &v, &mut buf, config ).unwrap( );
+      assert_eq!( &buf[ ..n ], &expected[ .. ] );
+      assert!( buf[ n.. ].iter( ).all( |&b| b == 0xAA ) );
+
+      // # Too small
+
+      for len in 0..expected.len( ) {
+        let mut buf = vec![ 0u8; len ];
+        let res = crate::serialize_to_slice_with( &v, &mut buf, config );
+        assert!( matches!( res, Err( ser::Error::BufferTooSmall ) ) );
+      }
+      let mut buf = vec![ 0u8; expected.len( ) ];
+      assert_eq!( crate::serialize_to_slice_with( &v, &mut buf, config ).unwrap( ), expected.len( ) );
+    }
+  }
+
+  #[test]
+  pub fn test_serialized_size( ) {
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Frame< 'a > { seq: u32, name: &'a str, xs: UnsizedSeq< 'a, u64 >, map: BTreeMap< u16, Option< i64 > > }
+
+    let xs: Vec< u64 > = ( 0..300 ).map( |i| i * 1000 ).collect( );
+    let map = ( 0..10 ).map( |i| ( i, Some( -( i as i64 ) ) ) ).collect( );
+    let v = Frame { seq: 9, name: "frame", xs: UnsizedSeq( &xs ), map };
+
+    for config in [ Config::default( ), Config::varint( ), Config { byte_order: ByteOrder::Big, seq_len: IntWidth::U16, ..Config::default( ) } ] {
+      let expected = crate::serialize_with( &v, config ).unwrap( );
+      assert_eq!( crate::serialized_size_with( &v, config ).unwrap( ), expected.len( ) );
+    }
+    assert_eq!( crate::serialized_size( &() ).unwrap( ), 0 );
+
+    // # Errors
+
+    assert!( matches!( crate::serialized_size( &Opcode( 300 ) ), Err( ser::Error::ExceedEnumVariant ) ) );
+    let config = Config { seq_len: IntWidth::U8, ..Config::default( ) };
+    assert!( matches!( crate::serialized_size_with( &vec![ 0u8; 256 ], config ), Err( ser::Error::ExceedSeqLen ) ) );
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  pub fn test_reader( ) {
+    use std::collections::BTreeMap;
+    use std::io;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Record { id: u64, name: String, tags: Vec< char >, blob: serde_bytes_like::Bytes, extra: BTreeMap< String, Option< i8 > > }
+
+    /// Yields at most one byte per read, then fails
+    struct Trickle< 'a > { data: &'a [u8], fail: bool }
+
+    impl io::Read for Trickle< '_ > {
+      fn read( &mut self, buf: &mut [u8] ) -> io::Result< usize > {
+        if self.data.is_empty( ) && self.fail {
+          return Err( io::Error::other( "connection reset" ) );
+        }
+        let n = self.data.len( ).min( buf.len( ) ).min( 1 );
+        buf[ ..n ].copy_from_slice( &self.data[ ..n ] );
+        self.data = &self.data[ n.. ];
+        Ok( n )
+      }
+    }
+
+    let v = Record {
+      id: 7,
+      name: "größe".to_owned( ),
+      tags: vec![ 'a', 'ß', '€', '😀' ],
+      blob: serde_bytes_like::Bytes( vec![ 1, 2, 3 ] ),
+      extra: [ ( "x".to_owned( ), Some( -1 ) ), ( "y".to_owned( ), None ) ].into_iter( ).collect( )
+    };
+
+    // # Deserialize
+
+    for config in [ Config::default( ), Config::varint( ), Config { strict: true, ..Config::default( ) } ] {
+      let data = crate::serialize_with( &v, config ).unwrap( );
+      let res: Record = crate::deserialize_from_reader_with( Trickle { data: &data, fail: false }, config ).unwrap( );
+      assert_eq!( res, v );
+    }
+
+    // consecutive values are read from the same reader
+    let mut data = crate::serialize( &v ).unwrap( );
+    data.extend( crate::serialize( &"next" ).unwrap( ) );
+    let mut reader = &data[ .. ];
+    #[cfg(feature = "std")]
+    assert_eq!( crate::deserialize_from_reader::< _, Record >( &mut reader ).unwrap( ), v );
+    #[cfg(feature = "std")]
+    assert_eq!( crate::deserialize_from_reader::< _, String >( &mut reader ).unwrap( ), "next" );
+    assert!( reader.is_empty( ) );
+
+    // # Errors
+
+    let data = crate::serialize( &v ).unwrap( );
+    let truncated = &data[ ..data.len( ) - 1 ];
+    #[cfg(feature = "std")]
+    assert!( matches!( crate::deserialize_from_reader::< _, Record >( truncated ), Err( de::Error::UnexpectedEof ) ) );
+    let res = crate::deserialize_from_reader::< _, Record >( Trickle { data: truncated, fail: true } );
+    assert!( matches!( res, Err( de::Error::Io( ref err ) ) if err.to_string( ) == "connection reset" ) );
+    // a bogus length does not allocate up front
+    let data = crate::serialize( &u32::MAX ).unwrap( );
+    #[cfg(feature = "std")]
+    assert!( matches!( crate::deserialize_from_reader::< _, String >( &data[ .. ] ), Err( de::Error::UnexpectedEof ) ) );
+
+    let strict = Config { strict: true, ..Config::default( ) };
+    let data = crate::serialize( &( 1u8, 2u8 ) ).unwrap( );
+    #[cfg(feature = "std")]
+    assert!( matches!( crate::deserialize_from_reader_with::< _, u8 >( &data[ .. ], strict ), Err( de::Error::TrailingBytes ) ) );
+    let data = crate::serialize( &vec![ ( "a", 1u8 ), ( "a", 2u8 ) ] ).unwrap( );
+    let res = crate::deserialize_from_reader_with::< _, BTreeMap< String, u8 > >( &data[ .. ], strict );
+    assert!( matches!( res, Err( de::Error::DuplicateMapKey ) ) );
+  }
+
+  #[test]
+  pub fn test_strict( ) {
+    use std::collections::BTreeMap;
+
+    /// Only reads the first element of a sequence
+    #[derive(Debug, PartialEq)]
+    struct First( u32 );
+
+    impl< 'de > Deserialize< 'de > for First {
+      fn deserialize< D: serde::Deserializer< 'de > >( d: D ) -> Result< Self, D::Error > {
+        struct FirstVisitor;
+
+        impl< 'de > serde::de::Visitor< 'de > for FirstVisitor {
+          type Value = First;
+
+          fn expecting( &self, f: &mut std::fmt::Formatter ) -> std::fmt::Result {
+            write!( f, "a non-empty sequence" )
+          }
+
+          fn visit_seq< A: serde::de::SeqAccess< 'de > >( self, mut seq: A ) -> Result< First, A::Error > {
+            let x = seq.next_element( )?.ok_or_else( || serde::de::Error::custom( "empty" ) )?;
+            Ok( First( x ) )
+          }
+        }
+
+        d.deserialize_seq( FirstVisitor )
+      }
+    }
+
+    let strict = Config { strict: true, ..Config::default( ) };
+
+    // # Trailing bytes
+
+    let mut data = crate::serialize( &7u32 ).unwrap( );
+    assert_eq!( crate::deserialize_with::< u32 >( &data, strict ).unwrap( ), 7 );
+    data.push( 0 );
+    assert_eq!( crate::deserialize::< u32 >( &data ).unwrap( ), 7 );
+    assert!( matches!( crate::deserialize_with::< u32 >( &data, strict ), Err( de::Error::TrailingBytes ) ) );
+
+    // # Unconsumed elements
+
+    let data = crate::serialize( &vec![ 1u32 ] ).unwrap( );
+    assert_eq!( crate::deserialize_with::< First >( &data, strict ).unwrap( ), First( 1 ) );
+    let data = crate::serialize( &vec![ 1u32, 2 ] ).unwrap( );
+    assert_eq!( crate::deserialize::< First >( &data ).unwrap( ), First( 1 ) );
+    assert!( matches!( crate::deserialize_with::< First >( &data, strict ), Err( de::Error::UnconsumedElements ) ) );
+
+    // # Duplicate map keys
+
+    let m: BTreeMap< String, u8 > = [ ( "a".to_owned( ), 1 ), ( "b".to_owned( ), 2 ) ].into_iter( ).collect( );
+    let data = crate::serialize( &m ).unwrap( );
+    assert_eq!( crate::deserialize_with::< BTreeMap< String, u8 > >( &data, strict ).unwrap( ), m );
+    let dup = vec![ ( "a", 1u8 ), ( "a", 2u8 ) ];
+    let data = crate::serialize( &dup ).unwrap( );
+    // a sequence of pairs is encoded like a map
+    assert_eq!( crate::deserialize::< BTreeMap< String, u8 > >( &data ).unwrap( ).len( ), 1 );
+    assert!( matches!( crate::deserialize_with::< BTreeMap< String, u8 > >( &data, strict ), Err( de::Error::DuplicateMapKey ) ) );
+
+    // Sets are plain sequences to the deserializer, so their duplicates are
+    // dropped by the set, not rejected
+    let data = crate::serialize( &vec![ 1u32, 1 ] ).unwrap( );
+    let set = crate::deserialize_with::< std::collections::BTreeSet< u32 > >( &data, strict ).unwrap( );
+    assert_eq!( set.into_iter( ).collect::< Vec< _ > >( ), [ 1 ] );
+  }
+
+  #[test]
+  pub fn test_limits( ) {
+    use crate::{Limit, Limits};
+
+    /// Records the `size_hint` of a sequence
+    struct Hint( Option< usize > );
+
+    impl< 'de > Deserialize< 'de > for Hint {
+      fn deserialize< D: serde::Deserializer< 'de > >( d: D ) -> Result< Self, D::Error > {
+        struct HintVisitor;
+
+        impl< 'de > serde::de::Visitor< 'de > for HintVisitor {
+          type Value = Hint;
+
+          fn expecting( &self, f: &mut std::fmt::Formatter ) -> std::fmt::Result {
+            write!( f, "a sequence" )
+          }
+
+          fn visit_seq< A: serde::de::SeqAccess< 'de > >( self, mut seq: A ) -> Result< Hint, A::Error > {
+            let hint = seq.size_hint( );
+            while seq.next_element::< u8 >( )?.is_some( ) { }
+            Ok( Hint( hint ) )
+          }
+        }
+
+        d.deserialize_seq( HintVisitor )
+      }
+    }
+
+    let limited = | limits: Limits | Config { limits, ..Config::default( ) };
+
+    // # Total bytes
+
+    let data = crate::serialize( &( 1u32, "abc" ) ).unwrap( );
+    let config = limited( Limits { max_bytes: Some( data.len( ) as u64 ), ..Limits::default( ) } );
+    assert_eq!( crate::from_slice_with::< ( u32, &str ) >( &data, config ).unwrap( ), ( 1, "abc" ) );
+    let config = limited( Limits { max_bytes: Some( data.len( ) as u64 - 1 ), ..Limits::default( ) } );
+    assert!( matches!( crate::from_slice_with::< ( u32, &str ) >( &data, config ), Err( de::Error::LimitExceeded( Limit::MaxBytes ) ) ) );
+
+    // A bogus length is rejected before its payload is read
+    #[cfg(feature = "std")]
+    {
+      let config = limited( Limits { max_bytes: Some( 1 << 20 ), ..Limits::default( ) } );
+      let res = crate::deserialize_from_reader_with::< _, Vec< u8 > >( std::io::repeat( 0xFF ), config );
+      assert!( matches!( res, Err( de::Error::LimitExceeded( Limit::MaxBytes ) ) ) );
+    }
+
+    // # String and byte lengths
+
+    let config = limited( Limits { max_len: Some( 3 ), ..Limits::default( ) } );
+    let data = crate::serialize( &"abc" ).unwrap( );
+    assert_eq!( crate::deserialize_with::< String >( &data, config ).unwrap( ), "abc" );
+    let data = crate::serialize( &"abcd" ).unwrap( );
+    assert!( matches!( crate::deserialize_with::< String >( &data, config ), Err( de::Error::LimitExceeded( Limit::MaxLen ) ) ) );
+    assert!( matches!( crate::from_slice_with::< &str >( &data, config ), Err( de::Error::LimitExceeded( Limit::MaxLen ) ) ) );
+    let data = crate::serialize( &serde_bytes_like::Bytes( vec![ 0; 4 ] ) ).unwrap( );
+    assert!( matches!( crate::deserialize_with::< serde_bytes_like::Bytes >( &data, config ), Err( de::Error::LimitExceeded( Limit::MaxLen ) ) ) );
+
+    // # Element counts
+
+    let config = limited( Limits { max_elements: Some( 2 ), ..Limits::default( ) } );
+    let data = crate::serialize( &vec![ 1u8, 2 ] ).unwrap( );
+    assert_eq!( crate::deserialize_with::< Vec< u8 > >( &data, config ).unwrap( ), [ 1, 2 ] );
+    let data = crate::serialize( &vec![ 1u8, 2, 3 ] ).unwrap( );
+    assert!( matches!( crate::deserialize_with::< Vec< u8 > >( &data, config ), Err( de::Error::LimitExceeded( Limit::MaxElements ) ) ) );
+    let data = crate::serialize( &vec![ ( 1u8, 1u8 ), ( 2, 2 ), ( 3, 3 ) ] ).unwrap( );
+    let res = crate::deserialize_with::< std::collections::BTreeMap< u8, u8 > >( &data, config );
+    assert!( matches!( res, Err( de::Error::LimitExceeded( Limit::MaxElements ) ) ) );
+    // Tuples have no length prefix
+    assert_eq!( crate::deserialize_with::< ( u8, u8, u8 ) >( &[ 1, 2, 3 ], config ).unwrap( ), ( 1, 2, 3 ) );
+
+    // # Size hint
+
+    let data = crate::serialize( &vec![ 0u8; 5000 ] ).unwrap( );
+    assert_eq!( crate::deserialize::< Hint >( &data ).unwrap( ).0, Some( 4096 ) );
+    let config = limited( Limits { max_size_hint: 16, ..Limits::default( ) } );
+    let data = crate::serialize( &vec![ 0u8; 10 ] ).unwrap( );
+    assert_eq!( crate::deserialize_with::< Hint >( &data, config ).unwrap( ).0, Some( 10 ) );
+    let data = crate::serialize( &vec![ 0u8; 17 ] ).unwrap( );
+    assert_eq!( crate::deserialize_with::< Hint >( &data, config ).unwrap( ).0, Some( 16 ) );
+  }
+
+  #[test]
+  pub fn test_max_depth( ) {
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct List( Option< Box< List > > );
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Tree { Leaf, Node( Box< Tree >, u8 ), Wrap( Box< Tree > ), Named { child: Box< Tree > } }
+
+    fn list( depth: usize ) -> List {
+      ( 0..depth ).fold( List( None ), |l, _| List( Some( Box::new( l ) ) ) )
+    }
+
+    // # Default
+
+    let data = crate::serialize( &list( 128 ) ).unwrap( );
+    assert_eq!( crate::deserialize::< List >( &data ).unwrap( ), list( 128 ) );
+    assert!( matches!( crate::serialize( &list( 129 ) ), Err( ser::Error::DepthLimitExceeded ) ) );
+    let unbounded = Config { max_depth: usize::MAX, ..Config::default( ) };
+    let data = crate::serialize_with( &list( 129 ), unbounded ).unwrap( );
+    assert!( matches!( crate::deserialize::< List >( &data ), Err( de::Error::DepthLimitExceeded ) ) );
+    assert_eq!( crate::deserialize_with::< List >( &data, unbounded ).unwrap( ), list( 129 ) );
+
+    let config = Config { max_depth: 10, ..Config::default( ) };
+
+    // # Options
+
+    let data = crate::serialize_with( &list( 10 ), config ).unwrap( );
+    assert_eq!( crate::deserialize_with::< List >( &data, config ).unwrap( ), list( 10 ) );
+    assert!( matches!( crate::serialize_with( &list( 11 ), config ), Err( ser::Error::DepthLimitExceeded ) ) );
+    let data = crate::serialize( &list( 11 ) ).unwrap( );
+    assert!( matches!( crate::deserialize_with::< List >( &data, config ), Err( de::Error::DepthLimitExceeded ) ) );
+
+    // A crafted input fails instead of overflowing the stack
+    let data = vec![ 1u8; 1_000_000 ];
+    assert!( matches!( crate::deserialize::< List >( &data ), Err( de::Error::DepthLimitExceeded ) ) );
+
+    // # Enums and collections
+
+    // Each variant with content counts as one level, on both sides
+    let mut tree = Tree::Leaf;
+    for i in 0..10 {
+      tree = match i % 3 {
+        0 => Tree::Node( Box::new( tree ), 0 ),
+        1 => Tree::Wrap( Box::new( tree ) ),
+        _ => Tree::Named { child: Box::new( tree ) }
+      };
+    }
+    let data = crate::serialize_with( &tree, config ).unwrap( );
+    assert_eq!( crate::deserialize_with::< Tree >( &data, config ).unwrap( ), tree );
+    let tree = Tree::Wrap( Box::new( tree ) );
+    assert!( matches!( crate::serialize_with( &tree, config ), Err( ser::Error::DepthLimitExceeded ) ) );
+    let data = crate::serialize( &tree ).unwrap( );
+    assert!( matches!( crate::deserialize_with::< Tree >( &data, config ), Err( de::Error::DepthLimitExceeded ) ) );
+
+    // # Errors
+
+    // Levels entered before an error are left, so the serializer can be reused
+    let mut s = BinSerializer::new( config );
+    let deep = vec![ ( 0u8, list( 9 ) ) ];
+    assert!( matches!( deep.serialize( &mut s ), Err( ser::Error::DepthLimitExceeded ) ) );
+    let deep: BTreeMap< u8, Vec< List > > = [ ( 0, vec![ list( 9 ) ] ) ].into_iter( ).collect( );
+    assert!( matches!( deep.serialize( &mut s ), Err( ser::Error::DepthLimitExceeded ) ) );
+    list( 10 ).serialize( &mut s ).unwrap( );
+
+    // Likewise, the deserializer can skip a bad value and read on
+    /// An even number, which is discarded
+    #[derive(Debug)]
+    struct Even;
+
+    impl< 'de > Deserialize< 'de > for Even {
+      fn deserialize< D: serde::Deserializer< 'de > >( d: D ) -> Result< Self, D::Error > {
+        match u32::deserialize( d )? {
+          x if x % 2 == 0 => Ok( Even ),
+          _ => Err( serde::de::Error::custom( "odd" ) )
+        }
+      }
+    }
+
+    let strict = Config { strict: true, ..config };
+    let mut data = crate::serialize( &vec![ Some( 3u32 ) ] ).unwrap( );
+    data.extend( crate::serialize( &vec![ ( 0u8, 1u8 ), ( 0, 2 ) ] ).unwrap( ) );
+    data.extend( crate::serialize( &list( 10 ) ).unwrap( ) );
+    let mut d = BinDeserializer::new( &data[ .. ], strict );
+    assert!( matches!( Vec::< Option< Even > >::deserialize( &mut d ), Err( de::Error::Custom( _ ) ) ) );
+    assert!( matches!( BTreeMap::< u8, u8 >::deserialize( &mut d ), Err( de::Error::DuplicateMapKey ) ) );
+    // The value of the duplicate entry
+    assert_eq!( u8::deserialize( &mut d ).unwrap( ), 2 );
+    assert_eq!( List::deserialize( &mut d ).unwrap( ), list( 10 ) );
+  }
+
+  #[test]
+  pub fn test_error_context( ) {
+    use crate::{Path, Segment};
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Server { port: u16, name: String }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    enum Shape { Circle { radius: u32 }, Label( String ) }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Root { servers: Vec< Server >, shapes: ( u8, Shape ) }
+
+    fn server( name: &str ) -> Server {
+      Server { port: 80, name: name.to_owned( ) }
+    }
+
+    fn path( p: &Path ) -> String {
+      p.to_string( )
+    }
+
+    let config = Config { error_context: true, str_len: IntWidth::U8, ..Config::default( ) };
+    let long = "x".repeat( 300 );
+
+    // # Serialize
+
+    let v = Root { servers: vec![ server( "a" ), server( "b" ), server( "c" ), server( &long ) ], shapes: ( 0, Shape::Label( "a".to_owned( ) ) ) };
+    let err = crate::serialize_with( &v, config ).unwrap_err( );
+    let ser::Error::Context( context ) = &err else { panic!( "{:?}", err ) };
+    assert!( matches!( context.error, ser::Error::ExceedStringLen ) );
+    assert_eq!( path( &context.path ), "servers[3].name" );
+    assert_eq!( context.path.segments( ).copied( ).collect::< Vec< _ > >( ), [ Segment::Field( "servers" ), Segment::Index( 3 ), Segment::Field( "name" ) ] );
+    assert_eq!( err.to_string( ), "ExceedStringLen at servers[3].name" );
+
+    let v = Root { servers: vec![ ], shapes: ( 0, Shape::Label( long.clone( ) ) ) };
+    let err = crate::serialize_with( &v, config ).unwrap_err( );
+    let ser::Error::Context( context ) = &err else { panic!( "{:?}", err ) };
+    assert_eq!( path( &context.path ), "shapes[1].Label" );
+
+    // Without context, errors are unchanged
+    let plain = Config { error_context: false, ..config };
+    assert!( matches!( crate::serialize_with( &v, plain ), Err( ser::Error::ExceedStringLen ) ) );
+
+    // # Deserialize
+
+    let v = Root { servers: vec![ server( "a" ), server( "bc" ) ], shapes: ( 0, Shape::Circle { radius: 7 } ) };
+    let data = crate::serialize_with( &v, config ).unwrap( );
+    // servers: 4 (len) + 2 + 1 + 1 (a) + 2 + 1 + 2 (bc)
+    let name_offset = 4 + 2 + 1 + 1 + 2 + 1;
+    let mut bad = data.clone( );
+    bad[ name_offset ] = 0xFF;
+    let err = crate::deserialize_with::< Root >( &bad, config ).unwrap_err( );
+    let de::Error::Context( context ) = &err else { panic!( "{:?}", err ) };
+    assert!( matches!( context.error, de::Error::MalformedUtf8 ) );
+    assert!( matches!( err.inner( ), de::Error::MalformedUtf8 ) );
+    assert_eq!( path( &context.path ), "servers[1].name" );
+    assert_eq!( context.offset, name_offset as u64 + 2 );
+
+    // Truncated within a struct variant
+    let err = crate::deserialize_with::< Root >( &data[ ..data.len( ) - 1 ], config ).unwrap_err( );
+    let de::Error::Context( context ) = &err else { panic!( "{:?}", err ) };
+    assert!( matches!( context.error, de::Error::InsufficientData ) );
+    assert_eq!( path( &context.path ), "shapes[1].Circle.radius" );
+    assert_eq!( context.offset, data.len( ) as u64 - 4 );
+    assert_eq!( err.to_string( ), format!( "InsufficientData at shapes[1].Circle.radius (offset {})", data.len( ) - 4 ) );
+
+    // Self-describing values are located the same way
+    let tagged = Config { self_describing: true, ..config };
+    let tagged_data = crate::serialize_with( &v, tagged ).unwrap( );
+    let err = crate::deserialize_with::< Root >( &tagged_data[ ..tagged_data.len( ) - 1 ], tagged ).unwrap_err( );
+    let de::Error::Context( context ) = &err else { panic!( "{:?}", err ) };
+    assert!( matches!( context.error, de::Error::InsufficientData ) );
+    assert_eq!( path( &context.path ), "shapes[1].Circle.radius" );
+
+    let label = Root { servers: vec![ ], shapes: ( 0, Shape::Label( "a".to_owned( ) ) ) };
+    let mut tagged_bad = crate::serialize_with( &label, tagged ).unwrap( );
+    *tagged_bad.last_mut( ).unwrap( ) = 0xFF;
+    let err = crate::deserialize_with::< Root >( &tagged_bad, tagged ).unwrap_err( );
+    let de::Error::Context( context ) = &err else { panic!( "{:?}", err ) };
+    assert!( matches!( context.error, de::Error::MalformedUtf8 ) );
+    assert_eq!( path( &context.path ), "shapes[1].Label" );
+
+    // At the top level, only the offset is known
+    let err = crate::deserialize_with::< u32 >( &[ 1, 2 ], config ).unwrap_err( );
+    let de::Error::Context( context ) = &err else { panic!( "{:?}", err ) };
+    assert!( context.path.is_empty( ) );
+    assert_eq!( context.offset, 0 );
+    assert_eq!( err.to_string( ), "InsufficientData at offset 0" );
+
+    let strict = Config { strict: true, ..config };
+    let err = crate::deserialize_with::< u8 >( &[ 1, 2 ], strict ).unwrap_err( );
+    assert!( matches!( err.inner( ), de::Error::TrailingBytes ) );
+
+    let plain = Config { error_context: false, ..config };
+    assert!( matches!( crate::deserialize_with::< Root >( &bad, plain ), Err( de::Error::MalformedUtf8 ) ) );
+  }
+
+  #[test]
+  pub fn test_self_describing( ) {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Inner { x: u8, y: Option< String > }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape { Empty, Circle( u32 ), Rect( u16, u16 ), Named { name: String } }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Outer {
+      id: u64,
+      #[serde(flatten)]
+      inner: Inner,
+      shapes: Vec< Shape >,
+      pair: ( i8, char )
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(untagged)]
+    enum Untagged { Num( u32 ), Text( String ), Pair { a: bool, b: f64 } }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    enum Internal { Ping, Move { dx: i32, dy: i32 } }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Small { a: u8 }
+
+    fn roundtrip< T: Serialize + for< 'de > Deserialize< 'de > + PartialEq + std::fmt::Debug >( v: &T, config: Config ) {
+      let data = crate::serialize_with( v, config ).unwrap( );
+      assert_eq!( &crate::deserialize_with::< T >( &data, config ).unwrap( ), v );
+    }
+
+    let config = Config { self_describing: true, strict: true, byte_order: ByteOrder::Little, ..Config::default( ) };
+
+    // # Encoding
+
+    assert_eq!( crate::serialize_with( &Some( 7u16 ), config ).unwrap( ), [ 18, 8, 7, 0 ] );
+    assert_eq!( crate::serialize_with( &None::< u16 >, config ).unwrap( ), [ 17 ] );
+    // A map from field names to values
+    assert_eq!( crate::serialize_with( &Small { a: 7 }, config ).unwrap( ), [ 20, 1, 0, 0, 0, 15, 1, 0, 0, 0, b'a', 7, 7 ] );
+    // Unit variants are their name
+    assert_eq!( crate::serialize_with( &Shape::Empty, config ).unwrap( ), [ 15, 5, 0, 0, 0, b'E', b'm', b'p', b't', b'y' ] );
+    // Other variants are a map from their name to their content
+    assert_eq!( crate::serialize_with( &Shape::Circle( 1 ), config ).unwrap( ), [ 20, 1, 0, 0, 0, 15, 6, 0, 0, 0, b'C', b'i', b'r', b'c', b'l', b'e', 9, 1, 0, 0, 0 ] );
+
+    // # Roundtrip
+
+    let outer = Outer {
+      id: 1,
+      inner: Inner { x: 2, y: Some( "y".to_owned( ) ) },
+      shapes: vec![ Shape::Empty, Shape::Circle( 3 ), Shape::Rect( 4, 5 ), Shape::Named { name: "n".to_owned( ) } ],
+      pair: ( -6, 'é' )
+    };
+    for c in [ config, Config { self_describing: true, ..Config::varint( ) } ] {
+      roundtrip( &outer, c );
+      roundtrip( &Untagged::Num( 1 ), c );
+      roundtrip( &Untagged::Text( "t".to_owned( ) ), c );
+      roundtrip( &Untagged::Pair { a: true, b: 0.5 }, c );
+      roundtrip( &Internal::Ping, c );
+      roundtrip( &Internal::Move { dx: -1, dy: 2 }, c );
+    }
+
+    // Unknown fields are skipped with `deserialize_ignored_any`
+    let data = crate::serialize_with( &outer, config ).unwrap( );
+    let plain = Config { strict: false, ..config };
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Id { id: u64 }
+    assert_eq!( crate::deserialize_with::< Id >( &data, plain ).unwrap( ), Id { id: 1 } );
+
+    // # Errors
+
+    // Without the tags, the type cannot be recovered
+    let data = crate::serialize_with( &Untagged::Num( 1 ), Config::default( ) ).unwrap( );
+    assert!( matches!( crate::deserialize_with::< Untagged >( &data, Config::default( ) ), Err( de::Error::UnknownType ) ) );
+    assert!( matches!( crate::deserialize_with::< u8 >( &[ 99, 1 ], config ), Err( de::Error::MalformedTag ) ) );
+    // An enum must be a map with a single entry
+    assert!( matches!( crate::deserialize_with::< Shape >( &[ 20, 0, 0, 0, 0 ], config ), Err( de::Error::MalformedTag ) ) );
+  }
+
+  #[test]
+  pub fn test_human_readable( ) {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+    let ip = Ipv4Addr::new( 127, 0, 0, 1 );
+
+    // # Compact by default
+
+    assert_eq!( bin_serialize( &ip ), [ 127, 0, 0, 1 ] );
+    assert_eq!( bin_deserialize::< Ipv4Addr >( &[ 127, 0, 0, 1 ] ), ip );
+    // Variant `V4`, followed by the address and port
+    let addr = SocketAddr::new( IpAddr::V4( ip ), 8080 );
+    assert_eq!( bin_serialize( &addr ), [ &[ 0, 127, 0, 0, 1 ][ .. ], &8080u16.to_ne_bytes( ) ].concat( ) );
+
+    let values = [
+      SocketAddr::new( IpAddr::V4( ip ), 8080 ),
+      SocketAddr::new( IpAddr::V6( Ipv6Addr::LOCALHOST ), 443 )
+    ];
+    for addr in values {
+      assert_eq!( bin_deserialize::< SocketAddr >( &bin_serialize( &addr ) ), addr );
+      assert_eq!( bin_deserialize::< IpAddr >( &bin_serialize( &addr.ip( ) ) ), addr.ip( ) );
+    }
+
+    // # Text, when configured
+
+    let config = Config { human_readable: true, byte_order: ByteOrder::Little, ..Config::default( ) };
+    let data = crate::serialize_with( &ip, config ).unwrap( );
+    assert_eq!( data, [ &[ 9, 0, 0, 0 ][ .. ], b"127.0.0.1" ].concat( ) );
+    assert_eq!( crate::deserialize_with::< Ipv4Addr >( &data, config ).unwrap( ), ip );
+    for addr in values {
+      let data = crate::serialize_with( &addr, config ).unwrap( );
+      assert_eq!( crate::deserialize_with::< SocketAddr >( &data, config ).unwrap( ), addr );
+    }
+  }
+
+  #[test]
+  pub fn test_options( ) {
+    use crate::{CharEncoding, Options};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Msg { id: u32, flag: bool, c: char, name: String }
+
+    let v = Msg { id: 300, flag: true, c: 'é', name: "a".to_owned( ) };
+
+    // # Builder
+
+    let options = Options::new( ).byte_order( ByteOrder::Big ).varint( ).strict( true );
+    let config = options.config( );
+    assert_eq!( config.byte_order, ByteOrder::Big );
+    assert_eq!( config.str_len, IntWidth::Varint );
+    assert!( config.strict );
+    assert_eq!( Options::from( config ), options );
+    assert_eq!( Options::new( ).config( ), Config::default( ) );
+
+    // # Entry points
+
+    let data = options.serialize( &v ).unwrap( );
+    assert_eq!( data, crate::serialize_with( &v, config ).unwrap( ) );
+    assert_eq!( options.serialized_size( &v ).unwrap( ), data.len( ) );
+    assert_eq!( options.deserialize::< Msg >( &data ).unwrap( ), v );
+    #[cfg(feature = "std")]
+    assert_eq!( options.deserialize_from_reader::< _, Msg >( &data[ .. ] ).unwrap( ), v );
+    let abc = options.serialize( &"abc" ).unwrap( );
+    let s: &str = options.from_slice( &abc ).unwrap( );
+    assert_eq!( s, "abc" );
+    let mut buf = [0u8; 32];
+    let n = options.serialize_to_slice( &v, &mut buf ).unwrap( );
+    assert_eq!( &buf[ ..n ], &data[ .. ] );
+    let mut d = options.deserializer( &data );
+    assert_eq!( Msg::deserialize( &mut d ).unwrap( ), v );
+
+    // # Chars
+
+    let options = Options::new( ).byte_order( ByteOrder::Little ).char_encoding( CharEncoding::CodePoint );
+    assert_eq!( options.serialize( &'é' ).unwrap( ), [ 0xE9, 0, 0, 0 ] );
+    assert_eq!( options.deserialize::< char >( &[ 0xE9, 0, 0, 0 ] ).unwrap( ), 'é' );
+    assert_eq!( options.varint( ).serialize( &'é' ).unwrap( ), [ 0xE9, 0x01 ] );
+    // A surrogate
+    assert!( matches!( options.deserialize::< char >( &[ 0x00, 0xD8, 0, 0 ] ), Err( de::Error::MalformedChar ) ) );
+
+    // # Bools
+
+    assert!( matches!( Options::new( ).deserialize::< bool >( &[ 2 ] ), Err( de::Error::MalformedBool ) ) );
+    assert!( Options::new( ).lenient_bool( true ).deserialize::< bool >( &[ 2 ] ).unwrap( ) );
+    assert!( !Options::new( ).lenient_bool( true ).deserialize::< bool >( &[ 0 ] ).unwrap( ) );
+  }
+
+  #[test]
+  pub fn test_envelope( ) {
+    use crate::{CharEncoding, Options};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Msg { id: u32, c: char, name: String }
+
+    let v = Msg { id: 300, c: 'é', name: "a".to_owned( ) };
+
+    // # Header
+
+    let options = Options::new( ).byte_order( ByteOrder::Little ).envelope( true );
+    let data = options.serialize( &7u16 ).unwrap( );
+    assert_eq!( data, [ b'B', b'S', b'E', b'R', 1, 0, 0, 2, 3, 2, 2, 0, 0, 0, 7, 0 ] );
+    assert_eq!( options.serialized_size( &7u16 ).unwrap( ), data.len( ) );
+    assert_eq!( options.deserialize::< u16 >( &data ).unwrap( ), 7 );
+
+    // # Reconfiguration
+
+    // The reader adopts the encoding settings of the writer
+    let writer = Options::new( ).byte_order( ByteOrder::Big ).varint( ).char_encoding( CharEncoding::CodePoint ).self_describing( true ).envelope( true );
+    let reader = Options::new( ).envelope( true ).strict( true );
+    let data = writer.serialize( &v ).unwrap( );
+    assert_eq!( reader.deserialize::< Msg >( &data ).unwrap( ), v );
+    #[cfg(feature = "std")]
+    assert_eq!( reader.deserialize_from_reader::< _, Msg >( &data[ .. ] ).unwrap( ), v );
+    // The reader's other settings remain
+    let mut trailing = data.clone( );
+    trailing.push( 0 );
+    assert!( matches!( reader.deserialize::< Msg >( &trailing ), Err( de::Error::TrailingBytes ) ) );
+
+    let mut s = writer.serializer( );
+    s.write_envelope( ).unwrap( );
+    v.serialize( &mut s ).unwrap( );
+    let mut d = Options::new( ).deserializer( &data );
+    d.read_envelope( ).unwrap( );
+    assert_eq!( Msg::deserialize( &mut d ).unwrap( ), v );
+
+    // # Errors
+
+    let mut bad = data.clone( );
+    bad[ 0 ] = b'X';
+    assert!( matches!( reader.deserialize::< Msg >( &bad ), Err( de::Error::UnknownMagic ) ) );
+    let mut bad = data.clone( );
+    bad[ 4 ] = 2;
+    assert!( matches!( reader.deserialize::< Msg >( &bad ), Err( de::Error::UnsupportedVersion( 2 ) ) ) );
+    let mut bad = data.clone( );
+    bad[ 7 ] = 5;
+    assert!( matches!( reader.deserialize::< Msg >( &bad ), Err( de::Error::MalformedEnvelope ) ) );
+    let mut bad = data.clone( );
+    bad[ 13 ] = 0x80;
+    assert!( matches!( reader.deserialize::< Msg >( &bad ), Err( de::Error::MalformedEnvelope ) ) );
+    // Data without an envelope
+    let data = Options::new( ).serialize( &"without an envelope" ).unwrap( );
+    assert!( matches!( reader.deserialize::< String >( &data ), Err( de::Error::UnknownMagic ) ) );
+    assert!( matches!( reader.deserialize::< Msg >( b"BS" ), Err( de::Error::InsufficientData ) ) );
+
+    // Streams read the header like slices: located, and within the limits
+    let data = writer.serialize( &v ).unwrap( );
+    let mut bad = data.clone( );
+    bad[ 0 ] = b'X';
+    let located = reader.error_context( true );
+    let unknown_magic = |err: de::Error| {
+      let de::Error::Context( context ) = &err else { panic!( "{:?}", err ) };
+      assert!( matches!( context.error, de::Error::UnknownMagic ) );
+      assert_eq!( context.offset, 14 );
+    };
+    unknown_magic( located.deserialize::< Msg >( &bad ).unwrap_err( ) );
+    #[cfg(feature = "std")]
+    unknown_magic( located.deserialize_from_reader::< _, Msg >( &bad[ .. ] ).unwrap_err( ) );
+    let limited = reader.limits( crate::Limits { max_bytes: Some( 13 ), ..crate::Limits::default( ) } );
+    assert!( matches!( limited.deserialize::< Msg >( &data ), Err( de::Error::LimitExceeded( crate::Limit::MaxBytes ) ) ) );
+    #[cfg(feature = "std")]
+    assert!( matches!( limited.deserialize_from_reader::< _, Msg >( &data[ .. ] ), Err( de::Error::LimitExceeded( crate::Limit::MaxBytes ) ) ) );
+    let limited = reader.limits( crate::Limits { max_bytes: Some( data.len( ) as u64 - 1 ), ..crate::Limits::default( ) } );
+    assert!( matches!( limited.deserialize::< Msg >( &data ), Err( de::Error::LimitExceeded( crate::Limit::MaxBytes ) ) ) );
+    #[cfg(feature = "std")]
+    assert!( matches!( limited.deserialize_from_reader::< _, Msg >( &data[ .. ] ), Err( de::Error::LimitExceeded( crate::Limit::MaxBytes ) ) ) );
+    let limited = reader.limits( crate::Limits { max_bytes: Some( data.len( ) as u64 ), ..crate::Limits::default( ) } );
+    assert_eq!( limited.deserialize::< Msg >( &data ).unwrap( ), v );
+    #[cfg(feature = "std")]
+    assert_eq!( limited.deserialize_from_reader::< _, Msg >( &data[ .. ] ).unwrap( ), v );
+  }
+
+  #[test]
+  pub fn test_fingerprint( ) {
+    use std::collections::BTreeMap;
+    use crate::{fingerprint, Options};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct User { id: u32, name: String }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Renamed { id: u32, name: String }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Account { id: u32, email: String }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Wide { id: u64, name: String }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape { Circle( f64 ), Rect { w: f64, h: f64 } }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape2 { Circle( f64 ), Rect { w: f64, h: f32 } }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Tree { value: u8, children: Vec< Tree > }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum List { Cons( u8, Box< List > ), Nil }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Expr { Lit( i64 ), Add( Box< Expr >, Box< Expr > ), Neg( Box< Expr > ), Var( Name ) }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Name { Short( u8 ), Long( String ) }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Name2 { Short( u8 ), Long( Vec< u8 > ) }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Expr2 { Lit( i64 ), Add( Box< Expr2 >, Box< Expr2 > ), Neg( Box< Expr2 > ), Var( Name2 ) }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(try_from = "u8")]
+    struct Odd( u8 );
+    impl TryFrom< u8 > for Odd {
+      type Error = &'static str;
+      fn try_from( v: u8 ) -> Result< Self, Self::Error > {
+        if v % 2 == 1 { Ok( Odd( v ) ) } else { Err( "even" ) }
+      }
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Checked { id: std::num::NonZeroU32, odd: Odd, name: Option< String > }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(try_from = "u8")]
+    struct Never { x: u8 }
+    impl TryFrom< u8 > for Never {
+      type Error = &'static str;
+      fn try_from( _: u8 ) -> Result< Self, Self::Error > {
+        Err( "never" )
+      }
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Borrowed< 'a > { name: &'a str }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(untagged)]
+    enum Untagged { Num( u32 ), List( Vec< u8 > ) }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    enum Internal { A { x: u32 }, B }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Flat { id: u32, #[serde(flatten)] extra: BTreeMap< String, u32 > }
+
+    // # Shapes
+
+    let f = fingerprint::< User >( ).unwrap( );
+    assert_eq!( f, fingerprint::< User >( ).unwrap( ) );
+    // Type names do not affect the encoding
+    assert_eq!( f, fingerprint::< Renamed >( ).unwrap( ) );
+    assert_ne!( f, fingerprint::< Account >( ).unwrap( ) );
+    assert_ne!( f, fingerprint::< Wide >( ).unwrap( ) );
+    assert_ne!( f, fingerprint::< ( u32, String ) >( ).unwrap( ) );
+    // Owned and borrowed strings are encoded alike
+    assert_eq!( fingerprint::< String >( ).unwrap( ), fingerprint::< &str >( ).unwrap( ) );
+    assert_ne!( fingerprint::< Vec< u8 > >( ).unwrap( ), fingerprint::< Vec< u16 > >( ).unwrap( ) );
+    assert_ne!( fingerprint::< Option< u8 > >( ).unwrap( ), fingerprint::< u8 >( ).unwrap( ) );
+    assert_ne!( fingerprint::< BTreeMap< String, u8 > >( ).unwrap( ), fingerprint::< BTreeMap< String, i8 > >( ).unwrap( ) );
+    // Every variant is traced
+    assert_ne!( fingerprint::< Shape >( ).unwrap( ), fingerprint::< Shape2 >( ).unwrap( ) );
+    // Including those only reachable through other variants
+    assert_ne!( fingerprint::< Expr >( ).unwrap( ), fingerprint::< Expr2 >( ).unwrap( ) );
+    // Recursive types
+    assert_ne!( fingerprint::< Tree >( ).unwrap( ), fingerprint::< List >( ).unwrap( ) );
+    // Types that select their encoding by `is_human_readable`
+    assert_eq!( fingerprint::< std::net::Ipv4Addr >( ).unwrap( ), fingerprint::< [u8; 4] >( ).unwrap( ) );
+    // Types that reject every placeholder value
+    assert!( matches!( crate::fingerprint_with::< std::net::Ipv4Addr >( Options::new( ).human_readable( true ).config( ) ), Err( de::Error::UntraceableType ) ) );
+    assert!( matches!( crate::serialize_typed( &Never { x: 2 } ), Err( ser::Error::UntraceableType ) ) );
+    // Unless optional
+    assert_ne!( fingerprint::< Option< Never > >( ).unwrap( ), fingerprint::< Option< u8 > >( ).unwrap( ) );
+    // Types deserialized with `deserialize_any`, whose contents are not traced
+    assert_eq!( fingerprint::< Untagged >( ).unwrap( ), fingerprint::< serde::de::IgnoredAny >( ).unwrap( ) );
+    // Types that require map keys by name
+    assert!( matches!( fingerprint::< Internal >( ), Err( de::Error::UntraceableType ) ) );
+    assert!( matches!( fingerprint::< Flat >( ), Err( de::Error::UntraceableType ) ) );
+    assert!( matches!( crate::serialize_typed( &Internal::B ), Err( ser::Error::UntraceableType ) ) );
+
+    // # Checks
+
+    let v = User { id: 1, name: "a".to_owned( ) };
+    let data = crate::serialize_typed( &v ).unwrap( );
+    assert_eq!( data.len( ), 8 + crate::serialize( &v ).unwrap( ).len( ) );
+    assert_eq!( crate::deserialize_typed::< User >( &data ).unwrap( ), v );
+    assert!( matches!( crate::deserialize_typed::< Account >( &data ), Err( de::Error::TypeMismatch ) ) );
+    assert!( matches!( crate::deserialize_typed::< ( u32, String ) >( &data ), Err( de::Error::TypeMismatch ) ) );
+    let tree = Tree { value: 1, children: vec![ Tree { value: 2, children: vec![ ] } ] };
+    let options = Options::new( ).envelope( true ).varint( ).strict( true );
+    let data = options.serialize_typed( &tree ).unwrap( );
+    assert_eq!( options.deserialize_typed::< Tree >( &data ).unwrap( ), tree );
+    assert!( matches!( options.deserialize_typed::< List >( &data ), Err( de::Error::TypeMismatch ) ) );
+
+    // Types that reject some placeholder values
+    let v = Checked { id: std::num::NonZeroU32::new( 7 ).unwrap( ), odd: Odd( 3 ), name: Some( "n".to_owned( ) ) };
+    let data = crate::serialize_typed( &v ).unwrap( );
+    assert_eq!( crate::deserialize_typed::< Checked >( &data ).unwrap( ), v );
+    assert_ne!( fingerprint::< Checked >( ).unwrap( ), fingerprint::< ( u32, u8, Option< String > ) >( ).unwrap( ) );
+    let data = crate::serialize_typed( &None::< Never > ).unwrap( );
+    assert_eq!( crate::deserialize_typed::< Option< Never > >( &data ).unwrap( ), None );
+    let described = Options::new( ).self_describing( true );
+    for v in [ Untagged::Num( 3 ), Untagged::List( vec![ 1, 2 ] ) ] {
+      let data = described.serialize_typed( &v ).unwrap( );
+      assert_eq!( described.deserialize_typed::< Untagged >( &data ).unwrap( ), v );
+    }
+
+    // A precomputed fingerprint, e.g., of a type that borrows
+    let f = fingerprint::< Borrowed< '_ > >( ).unwrap( );
+    let mut s = options.serializer( );
+    s.write_fingerprint( f ).unwrap( );
+    Borrowed { name: "b" }.serialize( &mut s ).unwrap( );
+    let data: Vec< u8 > = s.into( );
+    let mut d = options.deserializer( &data );
+    d.check_fingerprint( f ).unwrap( );
+    assert_eq!( Borrowed::deserialize( &mut d ).unwrap( ), Borrowed { name: "b" } );
+    let mut d = options.deserializer( &data );
+    assert!( matches!( d.check_fingerprint( fingerprint::< List >( ).unwrap( ) ), Err( de::Error::TypeMismatch ) ) );
+  }
+
+  #[test]
+  pub fn test_checksum( ) {
+    use crate::Options;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Reading { sensor: u16, values: Vec< f32 >, label: String }
+
+    let v = Reading { sensor: 7, values: vec![ 1.5, -2.0, 3.25 ], label: "temperature".to_owned( ) };
+
+    // # CRC32C
+
+    assert_eq!( crate::checksum::crc32c( 0, b"123456789" ), 0xE306_9283 );
+    assert_eq!( crate::checksum::crc32c( crate::checksum::crc32c( 0, b"1234" ), b"56789" ), 0xE306_9283 );
+    assert_eq!( crate::checksum::crc32c( 0, &[ 0u8; 32 ] ), 0x8A91_36AA );
+
+    // # Trailer
+
+    let options = Options::new( ).checksum( true ).strict( true );
+    let data = options.serialize( &v ).unwrap( );
+    let plain = Options::new( ).serialize( &v ).unwrap( );
+    assert_eq!( data[ ..plain.len( ) ], plain[ .. ] );
+    assert_eq!( data.len( ), plain.len( ) + 4 );
+    assert_eq!( options.serialized_size( &v ).unwrap( ), data.len( ) );
+    assert_eq!( options.deserialize::< Reading >( &data ).unwrap( ), v );
+
+    let mut buf = [0u8; 64];
+    let n = options.serialize_to_slice( &v, &mut buf ).unwrap( );
+    assert_eq!( buf[ ..n ], data[ .. ] );
+    assert!( matches!( options.serialize_to_slice( &v, &mut buf[ ..data.len( ) - 1 ] ), Err( ser::Error::BufferTooSmall ) ) );
+
+    // # Streams
+
+    let options = options.strict( false );
+    #[cfg(feature = "std")]
+    {
+      let mut out = Vec::new( );
+      options.serialize_into_writer( &mut out, &v ).unwrap( );
+      options.serialize_into_writer( &mut out, &v ).unwrap( );
+      assert_eq!( out[ ..data.len( ) ], data[ .. ] );
+      let mut reader = &out[ .. ];
+      assert_eq!( options.deserialize_from_reader::< _, Reading >( &mut reader ).unwrap( ), v );
+      assert_eq!( options.deserialize_from_reader::< _, Reading >( &mut reader ).unwrap( ), v );
+      assert!( reader.is_empty( ) );
+      assert!( matches!( options.strict( true ).deserialize_from_reader::< _, Reading >( &out[ .. ] ), Err( de::Error::TrailingBytes ) ) );
+    }
+
+    // # Corruption
+
+    for i in 0..data.len( ) {
+      let mut bad = data.clone( );
+      bad[ i ] ^= 0x10;
+      assert!( matches!( options.deserialize::< Reading >( &bad ), Err( de::Error::ChecksumMismatch ) ) );
+    }
+    // The label length is corrupted, but the value is only decoded once verified
+    let mut bad = data.clone( );
+    bad[ data.len( ) - 4 - "temperature".len( ) - 4 ] = 3;
+    assert!( matches!( options.deserialize::< Reading >( &bad ), Err( de::Error::ChecksumMismatch ) ) );
+    // Readers verify after decoding, but before returning the value
+    let mut bad = data.clone( );
+    bad[ data.len( ) - 5 ] ^= 1;
+    #[cfg(feature = "std")]
+    assert!( matches!( options.deserialize_from_reader::< _, Reading >( &bad[ .. ] ), Err( de::Error::ChecksumMismatch ) ) );
+    #[cfg(feature = "std")]
+    assert!( matches!( options.deserialize_from_reader::< _, Reading >( &data[ ..data.len( ) - 2 ] ), Err( de::Error::UnexpectedEof ) ) );
+    assert!( matches!( options.deserialize::< u8 >( &[ 1, 2 ] ), Err( de::Error::InsufficientData ) ) );
+
+    // # Envelope
+
+    // The header records the trailer, so readers verify it without the option
+    let writer = Options::new( ).envelope( true ).checksum( true );
+    let reader = Options::new( ).envelope( true ).strict( true );
+    let data = writer.serialize( &v ).unwrap( );
+    assert_eq!( data[ 13 ] & 0b100, 0b100 );
+    assert_eq!( reader.deserialize::< Reading >( &data ).unwrap( ), v );
+    #[cfg(feature = "std")]
+    assert_eq!( reader.deserialize_from_reader::< _, Reading >( &data[ .. ] ).unwrap( ), v );
+    #[cfg(feature = "std")]
+    {
+      let mut w = writer.frame_writer( Vec::new( ) );
+      w.write( &v ).unwrap( );
+      let frames = w.into_inner( );
+      assert_eq!( reader.frame_reader( &frames[ .. ] ).read::< Reading >( ).unwrap( ).as_ref( ), Some( &v ) );
+    }
+    let mut bad = data.clone( );
+    bad[ 20 ] ^= 1;
+    assert!( matches!( reader.deserialize::< Reading >( &bad ), Err( de::Error::ChecksumMismatch ) ) );
+    #[cfg(feature = "std")]
+    assert!( matches!( reader.deserialize_from_reader::< _, Reading >( &bad[ .. ] ), Err( de::Error::ChecksumMismatch ) ) );
+    // Nor is a trailer expected where the header records none
+    let data = Options::new( ).envelope( true ).serialize( &v ).unwrap( );
+    assert_eq!( writer.deserialize::< Reading >( &data ).unwrap( ), v );
+    #[cfg(feature = "std")]
+    assert_eq!( writer.deserialize_from_reader::< _, Reading >( &data[ .. ] ).unwrap( ), v );
+    // Unknown flags are rejected
+    let mut bad = data.clone( );
+    bad[ 13 ] |= 0b1000;
+    assert!( matches!( reader.deserialize::< Reading >( &bad ), Err( de::Error::MalformedEnvelope ) ) );
+
+    // # Envelope and fingerprint
+
+    let options = options.envelope( true ).varint( );
+    let data = options.serialize_typed( &v ).unwrap( );
+    assert_eq!( options.deserialize_typed::< Reading >( &data ).unwrap( ), v );
+    let mut bad = data.clone( );
+    bad[ 5 ] ^= 1;
+    assert!( matches!( options.deserialize_typed::< Reading >( &bad ), Err( de::Error::ChecksumMismatch ) ) );
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  pub fn test_frames( ) {
+    use std::io;
+    use crate::{FrameReader, FrameWriter, Options};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Msg { Ping( u32 ), Text( String ), Quit }
+
+    /// Returns at most one byte per read, and is interrupted in between
+    struct Trickle< 'a > { data: &'a [u8], interrupt: bool }
+
+    impl io::Read for Trickle< '_ > {
+      fn read( &mut self, buf: &mut [u8] ) -> io::Result< usize > {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+          return Err( io::ErrorKind::Interrupted.into( ) );
+        }
+        let n = buf.len( ).min( self.data.len( ) ).min( 1 );
+        buf[ ..n ].copy_from_slice( &self.data[ ..n ] );
+        self.data = &self.data[ n.. ];
+        Ok( n )
+      }
+    }
+
+    let msgs = [ Msg::Ping( 1 ), Msg::Text( "hello".to_owned( ) ), Msg::Quit ];
+
+    // # Frames
+
+    let mut w = FrameWriter::new( Vec::new( ) );
+    for msg in &msgs {
+      w.write( msg ).unwrap( );
+    }
+    let data = w.into_inner( );
+    // Only the frame prefix has a fixed byte order
+    assert_eq!( data[ ..9 ], [ &[ 5, 0, 0, 0, 0 ][ .. ], &1u32.to_ne_bytes( ) ].concat( ) );
+    assert_eq!( data.len( ), 9 + 4 + 1 + 4 + 5 + 4 + 1 );
+
+    let mut r = FrameReader::new( Trickle { data: &data, interrupt: false } );
+    for msg in &msgs {
+      assert_eq!( r.read::< Msg >( ).unwrap( ).as_ref( ), Some( msg ) );
+    }
+    // A clean end between frames
+    assert!( r.read::< Msg >( ).unwrap( ).is_none( ) );
+    assert!( FrameReader::new( &[ ][ .. ] ).read::< Msg >( ).unwrap( ).is_none( ) );
+
+    // # Truncation
+
+    for n in [ 2, 4, 7 ] {
+      let mut r = FrameReader::new( Trickle { data: &data[ ..n ], interrupt: false } );
+      assert!( matches!( r.read::< Msg >( ), Err( de::Error::UnexpectedEof ) ) );
+    }
+
+    // # Leftover bytes
+
+    let mut w = FrameWriter::new( Vec::new( ) );
+    w.write( &( 1u8, 2u8 ) ).unwrap( );
+    let pair = w.into_inner( );
+    assert!( matches!( FrameReader::new( &pair[ .. ] ).read::< u8 >( ), Err( de::Error::TrailingBytes ) ) );
+    assert_eq!( FrameReader::new( &pair[ .. ] ).read::< ( u8, u8 ) >( ).unwrap( ), Some( ( 1, 2 ) ) );
+
+    // # Maximum frame length
+
+    let mut w = FrameWriter::new( Vec::new( ) ).max_frame_len( 8 );
+    w.write( &Msg::Ping( 2 ) ).unwrap( );
+    assert!( matches!( w.write( &Msg::Text( "too long".to_owned( ) ) ), Err( ser::Error::FrameTooLarge ) ) );
+    assert_eq!( w.get_ref( ).len( ), 9 );
+    let mut r = FrameReader::new( &data[ .. ] ).max_frame_len( 8 );
+    assert_eq!( r.read::< Msg >( ).unwrap( ), Some( Msg::Ping( 1 ) ) );
+    assert!( matches!( r.read::< Msg >( ), Err( de::Error::FrameTooLarge ) ) );
+    // A bogus length does not allocate up front
+    let mut r = FrameReader::new( &[ 0xFF, 0xFF, 0xFF, 0x7F, 0 ][ .. ] ).max_frame_len( usize::MAX );
+    assert!( matches!( r.read::< Msg >( ), Err( de::Error::UnexpectedEof ) ) );
+
+    // # Options
+
+    let options = Options::new( ).varint( ).envelope( true ).checksum( true ).strict( true );
+    let mut w = options.frame_writer( Vec::new( ) );
+    for msg in &msgs {
+      w.write( msg ).unwrap( );
+    }
+    let data = w.into_inner( );
+    let mut r = options.frame_reader( &data[ .. ] );
+    for msg in &msgs {
+      assert_eq!( r.read::< Msg >( ).unwrap( ).as_ref( ), Some( msg ) );
+    }
+    assert!( r.read::< Msg >( ).unwrap( ).is_none( ) );
+    let mut bad = data.clone( );
+    bad[ 20 ] ^= 1;
+    #[cfg(feature = "std")]
+    assert!( matches!( options.frame_reader( &bad[ .. ] ).read::< Msg >( ), Err( de::Error::ChecksumMismatch ) ) );
+  }
+
   // # Helpers
 
+  /// A unit-only enum with 300 variants, whose variant index is stored
+  #[derive(Debug, PartialEq)]
+  struct Opcode( u32 );
+
+  static OPCODE_VARIANTS: [&str; 300] = [ "op"; 300 ];
+
+  impl Serialize for Opcode {
+    fn serialize< S: serde::Serializer >( &self, s: S ) -> Result< S::Ok, S::Error > {
+      s.serialize_unit_variant( "Opcode", self.0, "op" )
+    }
+  }
+
+  impl< 'de > Deserialize< 'de > for Opcode {
+    fn deserialize< D: serde::Deserializer< 'de > >( d: D ) -> Result< Self, D::Error > {
+      use serde::de::{EnumAccess, VariantAccess};
+
+      struct OpcodeVisitor;
+
+      impl< 'de > serde::de::Visitor< 'de > for OpcodeVisitor {
+        type Value = Opcode;
+
+        fn expecting( &self, f: &mut std::fmt::Formatter ) -> std::fmt::Result {
+          write!( f, "an opcode" )
+        }
+
+        fn visit_enum< A: EnumAccess< 'de > >( self, data: A ) -> Result< Opcode, A::Error > {
+          let ( idx, variant ): ( u32, _ ) = data.variant( )?;
+          variant.unit_variant( )?;
+          Ok( Opcode( idx ) )
+        }
+      }
+
+      d.deserialize_enum( "Opcode", &OPCODE_VARIANTS, OpcodeVisitor )
+    }
+  }
+
+  /// Serializes a sequence without announcing its length up front
+  struct UnsizedSeq< 'a, T >( &'a [T] );
+
+  impl< T: Serialize > Serialize for UnsizedSeq< '_, T > {
+    fn serialize< S: serde::Serializer >( &self, s: S ) -> Result< S::Ok, S::Error > {
+      s.collect_seq( self.0.iter( ).filter( |_| true ) )
+    }
+  }
+
+  /// Serializes `&[u8]` through `serialize_bytes`, and borrows it back
+  mod borrowed_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize< S: Serializer >( v: &[u8], s: S ) -> Result< S::Ok, S::Error > {
+      s.serialize_bytes( v )
+    }
+
+    pub fn deserialize< 'de, D: Deserializer< 'de > >( d: D ) -> Result< &'de [u8], D::Error > {
+      <&[u8]>::deserialize( d )
+    }
+  }
+
+  /// A minimal stand-in for `serde_bytes`, which serializes through
+  /// `serialize_bytes`
+  mod serde_bytes_like {
+    #[cfg(not(feature = "std"))]
+    use std::{borrow::ToOwned, vec::Vec};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde::de::{self, Visitor};
+    use std::fmt;
+
+    #[derive(Debug, PartialEq)]
+    pub struct Bytes( pub Vec< u8 > );
+
+    impl Serialize for Bytes {
+      fn serialize< S: Serializer >( &self, s: S ) -> Result< S::Ok, S::Error > {
+        s.serialize_bytes( &self.0 )
+      }
+    }
+
+    impl< 'de > Deserialize< 'de > for Bytes {
+      fn deserialize< D: Deserializer< 'de > >( d: D ) -> Result< Self, D::Error > {
+        struct BytesVisitor;
+
+        impl< 'de > Visitor< 'de > for BytesVisitor {
+          type Value = Bytes;
+
+          fn expecting( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+            write!( f, "bytes" )
+          }
+
+          fn visit_bytes< E: de::Error >( self, v: &[u8] ) -> Result< Bytes, E > {
+            Ok( Bytes( v.to_owned( ) ) )
+          }
+
+          fn visit_byte_buf< E: de::Error >( self, v: Vec< u8 > ) -> Result< Bytes, E > {
+            Ok( Bytes( v ) )
+          }
+        }
+
+        d.deserialize_byte_buf( BytesVisitor )
+      }
+    }
+  }
+
   fn bin_serialize< V: Serialize >( v: &V ) -> Vec< u8 > {
     let mut s = BinSerializer::default( );
     v.serialize( &mut s ).unwrap( );
@@ -218,3 +1787,145 @@ mod tests {
     std::array::from_fn(|_| iter.next().unwrap())
   }
 }
+
+#[cfg(all(test, not(feature = "alloc")))]
+mod no_alloc_tests {
+  use core::fmt;
+  // external library imports
+  use serde::{Deserialize, Serialize};
+  // local imports
+  use crate::{Config, de, ser};
+
+  #[test]
+  pub fn test_slice_round_trip( ) {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape { Empty, Circle( u32 ), Rect { w: u16, h: u16 } }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Record< 'a > { id: u64, name: &'a str, tag: Option< char >, shapes: [Shape; 3] }
+
+    let v = Record { id: 7, name: "abc", tag: Some( 'x' ), shapes: [ Shape::Empty, Shape::Circle( 5 ), Shape::Rect { w: 2, h: 3 } ] };
+    let mut buf = [0u8; 128];
+    let n = crate::serialize_to_slice( &v, &mut buf ).unwrap( );
+    assert_eq!( n, crate::serialized_size( &v ).unwrap( ) );
+    assert_eq!( crate::from_slice::< Record >( &buf[ ..n ] ).unwrap( ), v );
+
+    // Strings are borrowed from the input
+    let w = crate::from_slice::< Record >( &buf[ ..n ] ).unwrap( );
+    assert!( buf[ ..n ].as_ptr_range( ).contains( &w.name.as_ptr( ) ) );
+
+    assert!( matches!( crate::serialize_to_slice( &v, &mut buf[ ..n - 1 ] ), Err( ser::Error::BufferTooSmall ) ) );
+    assert!( matches!( crate::from_slice::< Record >( &buf[ ..n - 1 ] ), Err( de::Error::InsufficientData ) ) );
+  }
+
+  #[test]
+  pub fn test_collect_str( ) {
+    /// Displayed in several pieces, so it is formatted with multiple writes
+    struct Version( u8, u8, u8 );
+
+    impl fmt::Display for Version {
+      fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+        write!( f, "{}.{}.{}", self.0, self.1, self.2 )
+      }
+    }
+
+    impl Serialize for Version {
+      fn serialize< S: serde::Serializer >( &self, s: S ) -> Result< S::Ok, S::Error > {
+        s.collect_str( self )
+      }
+    }
+
+    let mut buf = [0u8; 32];
+    let n = crate::serialize_to_slice( &Version( 1, 20, 44 ), &mut buf ).unwrap( );
+    let mut expected = [0u8; 32];
+    let m = crate::serialize_to_slice( &"1.20.44", &mut expected ).unwrap( );
+    assert_eq!( buf[ ..n ], expected[ ..m ] );
+    assert_eq!( crate::from_slice::< &str >( &buf[ ..n ] ).unwrap( ), "1.20.44" );
+
+    // Output errors are reported as such, rather than as formatting errors
+    assert!( matches!( crate::serialize_to_slice( &Version( 1, 20, 44 ), &mut buf[ ..n - 1 ] ), Err( ser::Error::BufferTooSmall ) ) );
+
+    // The length prefix is checked once the string is complete
+    struct Long;
+
+    impl Serialize for Long {
+      fn serialize< S: serde::Serializer >( &self, s: S ) -> Result< S::Ok, S::Error > {
+        s.collect_str( &format_args!( "{:300}", "" ) )
+      }
+    }
+
+    let mut buf = [0u8; 512];
+    let config = Config { str_len: crate::IntWidth::U8, ..Config::default( ) };
+    assert!( matches!( crate::serialize_to_slice_with( &Long, &mut buf, config ), Err( ser::Error::ExceedStringLen ) ) );
+  }
+
+  #[test]
+  pub fn test_custom( ) {
+    struct Unserializable;
+
+    impl Serialize for Unserializable {
+      fn serialize< S: serde::Serializer >( &self, _s: S ) -> Result< S::Ok, S::Error > {
+        Err( serde::ser::Error::custom( "unserializable" ) )
+      }
+    }
+
+    #[derive(Debug)]
+    struct Even( u32 );
+
+    impl< 'de > Deserialize< 'de > for Even {
+      fn deserialize< D: serde::Deserializer< 'de > >( d: D ) -> Result< Self, D::Error > {
+        match u32::deserialize( d )? {
+          x if x % 2 == 0 => Ok( Even( x ) ),
+          _ => Err( serde::de::Error::custom( "odd" ) )
+        }
+      }
+    }
+
+    // Without `alloc`, the message is dropped
+    let mut buf = [0u8; 8];
+    assert!( matches!( crate::serialize_to_slice( &Unserializable, &mut buf ), Err( ser::Error::Custom ) ) );
+    let n = crate::serialize_to_slice( &3u32, &mut buf ).unwrap( );
+    assert!( matches!( crate::from_slice::< Even >( &buf[ ..n ] ), Err( de::Error::Custom ) ) );
+    let n = crate::serialize_to_slice( &4u32, &mut buf ).unwrap( );
+    assert_eq!( crate::from_slice::< Even >( &buf[ ..n ] ).unwrap( ).0, 4 );
+  }
+
+  #[test]
+  pub fn test_strict_duplicate_keys( ) {
+    /// Counts the entries of a map
+    struct Entries( usize );
+
+    impl< 'de > Deserialize< 'de > for Entries {
+      fn deserialize< D: serde::Deserializer< 'de > >( d: D ) -> Result< Self, D::Error > {
+        struct EntriesVisitor;
+
+        impl< 'de > serde::de::Visitor< 'de > for EntriesVisitor {
+          type Value = Entries;
+
+          fn expecting( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+            write!( f, "a map" )
+          }
+
+          fn visit_map< A: serde::de::MapAccess< 'de > >( self, mut map: A ) -> Result< Entries, A::Error > {
+            let mut n = 0;
+            while map.next_entry::< u8, u8 >( )?.is_some( ) {
+              n += 1;
+            }
+            Ok( Entries( n ) )
+          }
+        }
+
+        d.deserialize_map( EntriesVisitor )
+      }
+    }
+
+    // a sequence of pairs is encoded like a map
+    let dup: &[( u8, u8 )] = &[ ( 1, 2 ), ( 1, 3 ) ];
+    let mut buf = [0u8; 16];
+    let n = crate::serialize_to_slice( &dup, &mut buf ).unwrap( );
+
+    // Keys cannot be stored, so duplicates go undetected, even in strict mode
+    let strict = Config { strict: true, ..Config::default( ) };
+    assert_eq!( crate::from_slice_with::< Entries >( &buf[ ..n ], strict ).unwrap( ).0, 2 );
+  }
+}